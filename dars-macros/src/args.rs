@@ -1,11 +1,15 @@
+use proc_macro2::Span;
 use syn::{
-    Lit,
+    Attribute, Expr, Ident, Lit, Meta, Token, Type,
     parse::{Parse, ParseStream},
 };
 
-#[derive(Debug)]
 pub struct Args {
     args: Option<String>,
+    /// `input = Type`, the input model of a signature declared on existing models.
+    pub input: Option<Type>,
+    /// `output = Type`, the output model of a signature declared on existing models.
+    pub output: Option<Type>,
 }
 
 impl Parse for Args {
@@ -13,27 +17,89 @@ impl Parse for Args {
         let mut args = String::new();
         while let Ok(lit) = input.parse::<Lit>() {
             match lit {
-                Lit::Str(str) => {
-                    for line in str.value().lines() {
-                        let line = line.trim();
-                        if !line.is_empty() {
-                            args.push_str(line);
-                            args.push('\n');
-                        }
-                    }
-                }
+                Lit::Str(str) => push_lines(&mut args, &str.value()),
                 _ => return Err(syn::Error::new(lit.span(), "Expected string literal")),
             }
         }
 
+        let mut models = [None, None];
+        while !input.is_empty() {
+            if !args.is_empty() || models.iter().any(Option::is_some) {
+                input.parse::<Token![,]>()?;
+                if input.is_empty() {
+                    break;
+                }
+            }
+            let key: Ident = input.parse()?;
+            input.parse::<Token![=]>()?;
+            let ty: Type = input.parse()?;
+            let model = match key.to_string().as_str() {
+                "input" => &mut models[0],
+                "output" => &mut models[1],
+                _ => {
+                    return Err(syn::Error::new(
+                        key.span(),
+                        format!("Invalid parameter: {key}"),
+                    ));
+                }
+            };
+            if model.replace(ty).is_some() {
+                return Err(syn::Error::new(
+                    key.span(),
+                    format!("Duplicate parameter: {key}"),
+                ));
+            }
+        }
+        let [model_input, model_output] = models;
+        if model_input.is_some() != model_output.is_some() {
+            return Err(syn::Error::new(
+                Span::call_site(),
+                "Expected both input and output models",
+            ));
+        }
+
         Ok(Args {
             args: (!args.is_empty()).then_some(args),
+            input: model_input,
+            output: model_output,
         })
     }
 }
 
-impl Into<Option<String>> for Args {
-    fn into(self) -> Option<String> {
-        self.args
+impl Args {
+    /// Arguments from the `///` doc comments, used when no string argument is given.
+    pub(crate) fn from_docs(attrs: &[Attribute]) -> Self {
+        let mut args = String::new();
+        for attr in attrs.iter().filter(|attr| attr.path().is_ident("doc")) {
+            if let Meta::NameValue(nv) = &attr.meta
+                && let Expr::Lit(lit) = &nv.value
+                && let Lit::Str(str) = &lit.lit
+            {
+                push_lines(&mut args, &str.value());
+            }
+        }
+
+        Args {
+            args: (!args.is_empty()).then_some(args),
+            input: None,
+            output: None,
+        }
+    }
+}
+
+/// Append the non-empty lines, trimmed.
+fn push_lines(args: &mut String, text: &str) {
+    for line in text.lines() {
+        let line = line.trim();
+        if !line.is_empty() {
+            args.push_str(line);
+            args.push('\n');
+        }
+    }
+}
+
+impl From<Args> for Option<String> {
+    fn from(args: Args) -> Self {
+        args.args
     }
 }
@@ -10,8 +10,15 @@ mod util;
 #[allow(non_snake_case)]
 #[proc_macro_attribute]
 pub fn Signature(args: TokenStream, input: TokenStream) -> TokenStream {
-    let sig = parse_macro_input!(input as signature::Signature);
     let args = parse_macro_input!(args as args::Args);
+    // Signature on existing models, `#[Signature(input = .., output = ..)]`
+    if args.input.is_some() {
+        return match signature::ModelSignature::parse(input.into(), args) {
+            Ok(sig) => TokenStream::from(quote!(#sig)),
+            Err(e) => e.into_compile_error().into(),
+        };
+    }
+    let sig = parse_macro_input!(input as signature::Signature);
     let sig = sig.with_instruction(args);
     TokenStream::from(quote!(#sig))
 }
@@ -21,6 +28,14 @@ pub fn Signature(args: TokenStream, input: TokenStream) -> TokenStream {
 pub fn Model(args: TokenStream, input: TokenStream) -> TokenStream {
     let model = parse_macro_input!(input as model::Model);
     let args = parse_macro_input!(args as args::Args);
+    if let Some(ty) = &args.input {
+        return syn::Error::new_spanned(
+            ty,
+            "Input and output models are only supported on signatures",
+        )
+        .into_compile_error()
+        .into();
+    }
     let model = model.with_args(args);
     TokenStream::from(quote!(#model))
 }
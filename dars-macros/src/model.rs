@@ -1,30 +1,52 @@
+use std::collections::HashSet;
+
 use proc_macro2::{Span, TokenStream};
-use quote::{ToTokens, quote};
+use quote::{ToTokens, format_ident, quote};
 use syn::{
-    Field, Ident, LitStr, Token, Type, Visibility, braced,
+    Attribute, Expr, ExprLit, Field, Fields, Generics, Ident, Lit, LitStr, Token, Type, Variant,
+    Visibility, braced,
     parse::{Parse, ParseStream},
-    spanned::Spanned,
 };
 
-use crate::util::parse_desc;
+use crate::args::Args;
+use crate::util::{
+    Constraint, check_field_name, check_pass_through, field_name, is_pass_through, model_generics,
+    parse_field_args, parse_generics, rename_all,
+};
 
 struct ModelField {
-    name: Ident,
+    ident: Ident,
+    /// Name of the field in JSON.
+    name: String,
+    /// Pass-through attributes of the field.
+    attrs: Vec<Attribute>,
     ty: Type,
     desc: Option<String>,
+    constraints: Vec<Constraint>,
+    default: Option<Expr>,
 }
 
 pub struct Model {
     description: Option<String>,
+    /// Pass-through attributes of the struct or enum.
+    attrs: Vec<Attribute>,
     vis: Visibility,
     name: Ident,
-    fields: Vec<ModelField>,
+    generics: Generics,
+    body: Body,
+}
+
+enum Body {
+    Struct(Vec<ModelField>),
+    /// Unit enum used as a field type.
+    Enum(Vec<Variant>),
 }
 
 impl Model {
     pub(crate) fn with_args(self, description: impl Into<Option<String>>) -> Self {
         Self {
-            description: description.into(),
+            // String arguments take precedence over doc comments
+            description: description.into().or(self.description),
             ..self
         }
     }
@@ -32,9 +54,15 @@ impl Model {
 
 impl Parse for Model {
     fn parse(input: ParseStream) -> syn::Result<Self> {
+        let attrs = input.call(Attribute::parse_outer)?;
         let vis = input.parse::<Visibility>()?;
+        if input.peek(Token![enum]) {
+            return parse_enum(input, attrs, vis);
+        }
+        check_pass_through(&attrs, "model")?;
         let _ = input.parse::<Token![struct]>()?;
         let name: Ident = input.parse()?;
+        let generics = parse_generics(input)?;
 
         // Extract the content of the struct
         let content;
@@ -43,38 +71,89 @@ impl Parse for Model {
         // Parse fields
         let raw_fields = content.parse_terminated(Field::parse_named, Token![,])?;
         let mut fields = Vec::with_capacity(raw_fields.len());
+        let mut names = HashSet::new();
+        let rename_all = rename_all(&attrs)?;
         for field in raw_fields {
-            let name = field
+            let ident = field
                 .ident
-                .ok_or(syn::Error::new(input.span(), "Missing field name"))?;
+                .clone()
+                .ok_or_else(|| syn::Error::new_spanned(&field, "Missing field name"))?;
 
-            let mut desc = None;
-            for attr in &field.attrs {
+            let mut args = None;
+            let mut attrs = Vec::new();
+            for attr in field.attrs {
                 if attr.path().is_ident("field") {
-                    desc = parse_desc(attr)?;
-                    break;
+                    if args.is_some() {
+                        return Err(syn::Error::new_spanned(
+                            attr,
+                            format!("Duplicate field attribute on field {ident}"),
+                        ));
+                    }
+                    args = Some(parse_field_args(&attr)?);
+                } else if is_pass_through(&attr) {
+                    attrs.push(attr);
+                } else {
+                    return Err(syn::Error::new_spanned(
+                        attr,
+                        format!("Unknown attribute on field {ident}"),
+                    ));
                 }
-                return Err(syn::Error::new(
-                    attr.span(),
-                    format!("Unknown attribute on field {name}"),
-                ));
             }
+            let args = args.unwrap_or_default();
+            let desc = args.desc.or_else(|| Args::from_docs(&attrs).into());
 
+            let name = field_name(&ident, &attrs, rename_all.as_ref())?;
+            check_field_name(&mut names, &name, &ident)?;
             fields.push(ModelField {
+                ident,
                 name,
+                attrs,
                 ty: field.ty,
                 desc,
+                constraints: args.constraints,
+                default: args.default,
             });
         }
         Ok(Model {
             vis,
             name,
-            fields,
-            description: None,
+            generics,
+            body: Body::Struct(fields),
+            description: Args::from_docs(&attrs).into(),
+            attrs,
         })
     }
 }
 
+fn parse_enum(input: ParseStream, attrs: Vec<Attribute>, vis: Visibility) -> syn::Result<Model> {
+    let _ = input.parse::<Token![enum]>()?;
+    let name: Ident = input.parse()?;
+
+    let content;
+    braced!(content in input);
+    let variants = content.parse_terminated(Variant::parse, Token![,])?;
+
+    check_pass_through(&attrs, "enum")?;
+    for variant in &variants {
+        if !matches!(variant.fields, Fields::Unit) {
+            return Err(syn::Error::new_spanned(
+                variant,
+                format!("Only unit variants are supported, found {}", variant.ident),
+            ));
+        }
+        check_pass_through(&variant.attrs, "variant")?;
+    }
+
+    Ok(Model {
+        description: Args::from_docs(&attrs).into(),
+        attrs,
+        vis,
+        name,
+        generics: Generics::default(),
+        body: Body::Enum(variants.into_iter().collect()),
+    })
+}
+
 impl ToTokens for Model {
     fn to_tokens(&self, tokens: &mut TokenStream) {
         let vis = &self.vis;
@@ -84,25 +163,76 @@ impl ToTokens for Model {
             .as_ref()
             .map(|d| d.as_str().trim())
             .unwrap_or_default();
-        let fields = self.fields.iter().map(|field| {
-            let name = &field.name;
+
+        let attrs = &self.attrs;
+        let model_fields = match &self.body {
+            Body::Struct(fields) => fields,
+            Body::Enum(variants) => {
+                let description = self
+                    .description
+                    .as_ref()
+                    .map(|d| quote!(#[schemars(description = #d.trim())]));
+                tokens.extend(quote! {
+                    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, da_rs::serde::Serialize, da_rs::serde::Deserialize, da_rs::schemars::JsonSchema)]
+                    #description
+                    #(#attrs)*
+                    #vis enum #name {
+                        #(#variants,)*
+                    }
+                });
+                return;
+            }
+        };
+        let fields = model_fields.iter().map(|field| {
+            let name = &field.ident;
+            let attrs = &field.attrs;
             let ty = &field.ty;
+            let constraints = field.constraints.iter().map(Constraint::to_schemars_attr);
+            let default = field.default.as_ref().map(|_| {
+                let path = format!("{}::{}", self.name, default_fn(&field.ident));
+                quote!(#[serde(default = #path)])
+            });
             match &field.desc {
                 Some(desc) => {
                     quote! {
+                        #(#attrs)*
                         #[schemars(description = #desc.trim())]
+                        #(#constraints)*
+                        #default
                         pub #name: #ty
                     }
                 }
                 None => {
                     quote! {
+                        #(#attrs)*
+                        #(#constraints)*
+                        #default
                         pub #name: #ty
                     }
                 }
             }
         });
-        let fields_names = self.fields.iter().map(|field| {
-            let name = LitStr::new(&field.name.to_string(), Span::call_site());
+        // Functions returning the defaults, referenced by `#[serde(default = ..)]`
+        let default_fns = model_fields.iter().filter_map(|field| {
+            let default = field.default.as_ref()?;
+            let ty = &field.ty;
+            let name = default_fn(&field.ident);
+            // String literals are converted, e.g. `default = "none"` on `String`,
+            // other expressions are typed by the field so integer literals infer
+            let value = match default {
+                Expr::Lit(ExprLit {
+                    lit: Lit::Str(_), ..
+                }) => quote!(::core::convert::Into::into(#default)),
+                _ => quote!(#default),
+            };
+            Some(quote! {
+                fn #name() -> #ty {
+                    #value
+                }
+            })
+        });
+        let fields_names = model_fields.iter().map(|field| {
+            let name = LitStr::new(&field.name, Span::call_site());
             match &field.desc {
                 Some(desc) => {
                     let desc = LitStr::new(desc.trim(), Span::call_site());
@@ -123,20 +253,34 @@ impl ToTokens for Model {
                 }
             }
         });
+        let generics = &self.generics;
+        let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+        let bounded = model_generics(generics);
+        let (_, _, model_where_clause) = bounded.split_for_impl();
         let expanded = quote! {
             #[derive(Debug, Clone, da_rs::serde::Serialize, da_rs::serde::Deserialize, da_rs::schemars::JsonSchema)]
             #[schemars(description = #description)]
-            #vis struct #name {
+            #(#attrs)*
+            #vis struct #name #generics #where_clause {
                 #(#fields,)*
             }
 
-            impl da_rs::Model for #name {
+            impl #impl_generics da_rs::Model for #name #ty_generics #model_where_clause {
                 #[inline]
                 fn fields() -> &'static [da_rs::Field] {
                     &[#(#fields_names,)*]
                 }
             }
+
+            impl #impl_generics #name #ty_generics #where_clause {
+                #(#default_fns)*
+            }
         };
         tokens.extend(expanded);
     }
 }
+
+/// Name of the function returning the default of a field.
+fn default_fn(field: &Ident) -> Ident {
+    format_ident!("__default_{}", field)
+}
@@ -1,28 +1,48 @@
+use std::collections::HashSet;
+
 use proc_macro2::{Span, TokenStream};
 use quote::{ToTokens, format_ident, quote};
 use syn::{
-    Field, Ident, LitStr, Token, Type, Visibility, braced,
+    Attribute, Expr, Field, Generics, Ident, ItemStruct, LitStr, Token, Type, Visibility, braced,
     parse::{Parse, ParseStream},
-    spanned::Spanned,
 };
 
-use crate::util::parse_desc;
+use crate::args::Args;
+use crate::util::{
+    Constraint, check_field_name, field_name, is_pass_through, model_generics, parse_field_args,
+    parse_generics, type_params, used_generics,
+};
 
 struct InputField {
+    ident: Ident,
+    /// Name of the field in JSON.
     name: String,
+    /// Pass-through attributes of the field.
+    attrs: Vec<Attribute>,
     ty: Type,
     desc: Option<String>,
+    constraints: Vec<Constraint>,
+    default: Option<Expr>,
 }
 
 struct OutputField {
+    ident: Ident,
+    /// Name of the field in JSON.
     name: String,
+    /// Pass-through attributes of the field.
+    attrs: Vec<Attribute>,
     ty: Type,
     desc: Option<String>,
+    constraints: Vec<Constraint>,
+    default: Option<Expr>,
 }
 
 pub struct Signature {
+    /// Doc comments of the struct.
+    attrs: Vec<Attribute>,
     vis: Visibility,
     name: Ident,
+    generics: Generics,
     instruction: Option<String>,
     inputs: Vec<InputField>,
     outputs: Vec<OutputField>,
@@ -31,7 +51,8 @@ pub struct Signature {
 impl Signature {
     pub(crate) fn with_instruction(self, instruction: impl Into<Option<String>>) -> Self {
         Self {
-            instruction: instruction.into(),
+            // String arguments take precedence over doc comments
+            instruction: instruction.into().or(self.instruction),
             ..self
         }
     }
@@ -39,9 +60,12 @@ impl Signature {
 
 impl Parse for Signature {
     fn parse(input: ParseStream) -> syn::Result<Self> {
+        let attrs = input.call(Attribute::parse_outer)?;
+        check_signature_attributes(&attrs)?;
         let vis = input.parse::<Visibility>()?;
         let _ = input.parse::<Token![struct]>()?;
         let name: Ident = input.parse()?;
+        let generics = parse_generics(input)?;
 
         // Extract the content of the struct
         let content;
@@ -52,41 +76,73 @@ impl Parse for Signature {
 
         let mut inputs = Vec::new();
         let mut outputs = Vec::new();
+        // Input and output names share the schemas of the signature
+        let mut names = HashSet::new();
         for field in fields {
-            let name = field.ident.expect("Missing field name").to_string();
-
-            if field.attrs.is_empty() {
-                panic!("Missing input/output attribute on field {}", name);
-            }
+            let ident = field
+                .ident
+                .clone()
+                .ok_or_else(|| syn::Error::new_spanned(&field, "Missing field name"))?;
 
+            let mut role = None;
+            let mut attrs = Vec::new();
             for attr in field.attrs {
-                if attr.path().is_ident("input") {
-                    inputs.push(InputField {
-                        name,
-                        ty: field.ty,
-                        desc: parse_desc(&attr)?,
-                    });
-                    break;
-                }
-                if attr.path().is_ident("output") {
-                    outputs.push(OutputField {
-                        name,
-                        ty: field.ty,
-                        desc: parse_desc(&attr)?,
-                    });
-                    break;
+                if attr.path().is_ident("input") || attr.path().is_ident("output") {
+                    if role.is_some() {
+                        return Err(syn::Error::new_spanned(
+                            attr,
+                            format!("Duplicate input/output attribute on field {ident}"),
+                        ));
+                    }
+                    role = Some(attr);
+                } else if is_pass_through(&attr) {
+                    attrs.push(attr);
+                } else {
+                    return Err(syn::Error::new_spanned(
+                        attr,
+                        format!("Unknown attribute on field {ident}"),
+                    ));
                 }
+            }
+            let Some(role) = role else {
                 return Err(syn::Error::new(
-                    attr.span(),
-                    format!("Unknown attribute on field {name}"),
+                    ident.span(),
+                    format!("Missing #[input] or #[output] attribute on field {ident}"),
                 ));
+            };
+
+            let name = field_name(&ident, &attrs, None)?;
+            check_field_name(&mut names, &name, &ident)?;
+            let args = parse_field_args(&role)?;
+            if role.path().is_ident("input") {
+                inputs.push(InputField {
+                    ident,
+                    name,
+                    attrs,
+                    ty: field.ty,
+                    desc: args.desc,
+                    constraints: args.constraints,
+                    default: args.default,
+                });
+            } else {
+                outputs.push(OutputField {
+                    ident,
+                    name,
+                    attrs,
+                    ty: field.ty,
+                    desc: args.desc,
+                    constraints: args.constraints,
+                    default: args.default,
+                });
             }
         }
 
         Ok(Signature {
+            instruction: Args::from_docs(&attrs).into(),
+            attrs,
             vis,
             name,
-            instruction: None,
+            generics,
             inputs,
             outputs,
         })
@@ -103,46 +159,33 @@ impl ToTokens for Signature {
 
         let name = &self.name;
         let vis = &self.vis;
+        let attrs = &self.attrs;
 
         // Input fields
         let input_struct = format_ident!("{}Input", self.name);
         let inputs = self.inputs.iter().map(|input| {
-            let name = Ident::new(&input.name, Span::call_site());
+            let name = &input.ident;
+            let attrs = &input.attrs;
             let ty = input.ty.clone();
-            match &input.desc {
-                Some(desc) => {
-                    let desc = LitStr::new(desc, Span::call_site());
-                    quote! {
-                        #[field(desc = #desc)]
-                        pub #name: #ty
-                    }
-                }
-                None => {
-                    quote! {
-                        pub #name: #ty
-                    }
-                }
+            let args = field_args(&input.desc, &input.constraints, &input.default);
+            quote! {
+                #(#attrs)*
+                #[field(#(#args),*)]
+                pub #name: #ty
             }
         });
 
         // Output fields
         let output_struct = format_ident!("{}Output", self.name);
         let outputs = self.outputs.iter().map(|output| {
-            let name = Ident::new(&output.name, Span::call_site());
+            let name = &output.ident;
+            let attrs = &output.attrs;
             let ty = output.ty.clone();
-            match &output.desc {
-                Some(desc) => {
-                    let desc = LitStr::new(desc, Span::call_site());
-                    quote! {
-                        #[field(desc = #desc)]
-                        pub #name: #ty
-                    }
-                }
-                None => {
-                    quote! {
-                        pub #name: #ty
-                    }
-                }
+            let args = field_args(&output.desc, &output.constraints, &output.default);
+            quote! {
+                #(#attrs)*
+                #[field(#(#args),*)]
+                pub #name: #ty
             }
         });
 
@@ -159,40 +202,55 @@ impl ToTokens for Signature {
                 }
             });
 
+        // Each model only takes the type parameters used by its fields
+        let input_generics = used_generics(&self.generics, self.inputs.iter().map(|f| &f.ty));
+        let (_, input_ty_generics, input_where_clause) = input_generics.split_for_impl();
+        let output_generics = used_generics(&self.generics, self.outputs.iter().map(|f| &f.ty));
+        let (_, output_ty_generics, output_where_clause) = output_generics.split_for_impl();
+
+        let generics = &self.generics;
+        let (_, _, where_clause) = generics.split_for_impl();
+        let bounded = model_generics(generics);
+        let (impl_generics, ty_generics, bounded_where_clause) = bounded.split_for_impl();
+        let params = type_params(generics);
+
         let expanded = quote! {
             // Input model struct
             #[Model]
-            #vis struct #input_struct {
+            #vis struct #input_struct #input_generics #input_where_clause {
                 #(#inputs,)*
             }
 
             // Output model struct
             #[Model]
-            #vis struct #output_struct {
+            #vis struct #output_struct #output_generics #output_where_clause {
                 #(#outputs,)*
             }
 
             // Base signature struct
+            #(#attrs)*
             #[derive(Debug)]
-            #vis struct #name {
+            #vis struct #name #generics #where_clause {
                 instruction: String,
                 fields: std::collections::HashMap<String, da_rs::schemars::Schema>,
+                _types: ::core::marker::PhantomData<fn() -> (#(#params,)*)>,
             }
 
-            impl #name {
+            impl #impl_generics #name #ty_generics #bounded_where_clause {
                 #vis fn new() -> Self {
                     Self {
                         instruction: #instruction.into(),
                         fields: std::collections::HashMap::from_iter([
                             #(#fields,)*
                         ]),
+                        _types: ::core::marker::PhantomData,
                     }
                 }
             }
 
-            impl da_rs::Signature for #name {
-                type Input = #input_struct;
-                type Output = #output_struct;
+            impl #impl_generics da_rs::Signature for #name #ty_generics #bounded_where_clause {
+                type Input = #input_struct #input_ty_generics;
+                type Output = #output_struct #output_ty_generics;
 
                 #[inline(always)]
                 fn instruction(&self) -> &str {
@@ -201,12 +259,12 @@ impl ToTokens for Signature {
 
                 #[inline]
                 fn input_fields(&self) -> &[da_rs::Field] {
-                    <#input_struct as da_rs::Model>::fields()
+                    <Self::Input as da_rs::Model>::fields()
                 }
 
                 #[inline]
                 fn output_fields(&self) -> &[da_rs::Field] {
-                    <#output_struct as da_rs::Model>::fields()
+                    <Self::Output as da_rs::Model>::fields()
                 }
 
                 #[inline]
@@ -218,3 +276,145 @@ impl ToTokens for Signature {
         tokens.extend(expanded);
     }
 }
+
+/// Signature on existing models, `#[Signature(input = Ticket, output = Triage)]`.
+pub struct ModelSignature {
+    /// Doc comments of the struct.
+    attrs: Vec<Attribute>,
+    vis: Visibility,
+    name: Ident,
+    instruction: Option<String>,
+    input: Type,
+    output: Type,
+}
+
+impl ModelSignature {
+    pub(crate) fn parse(item: TokenStream, args: Args) -> syn::Result<Self> {
+        let item: ItemStruct = syn::parse2(item)?;
+        check_signature_attributes(&item.attrs)?;
+        if !item.generics.params.is_empty() {
+            return Err(syn::Error::new_spanned(
+                &item.generics,
+                "Generic parameters are not supported with input and output models",
+            ));
+        }
+        if !item.fields.is_empty() {
+            return Err(syn::Error::new_spanned(
+                &item.fields,
+                "Fields are declared by the input and output models",
+            ));
+        }
+
+        let (Some(input), Some(output)) = (args.input.clone(), args.output.clone()) else {
+            return Err(syn::Error::new(
+                Span::call_site(),
+                "Expected both input and output models",
+            ));
+        };
+        Ok(ModelSignature {
+            // String arguments take precedence over doc comments
+            instruction: Option::from(args).or(Args::from_docs(&item.attrs).into()),
+            attrs: item.attrs,
+            vis: item.vis,
+            name: item.ident,
+            input,
+            output,
+        })
+    }
+}
+
+impl ToTokens for ModelSignature {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let instruction = self
+            .instruction
+            .as_ref()
+            .map(|s| s.trim().to_string())
+            .unwrap_or_default();
+        let attrs = &self.attrs;
+        let vis = &self.vis;
+        let name = &self.name;
+        let input = &self.input;
+        let output = &self.output;
+
+        tokens.extend(quote! {
+            #(#attrs)*
+            #[derive(Debug)]
+            #vis struct #name {
+                instruction: String,
+                fields: std::collections::HashMap<String, da_rs::schemars::Schema>,
+            }
+
+            impl #name {
+                #vis fn new() -> Self {
+                    // Fields are looked up by name, so the models must not share names
+                    da_rs::check_distinct_fields(
+                        <#input as da_rs::Model>::fields(),
+                        <#output as da_rs::Model>::fields(),
+                    );
+                    Self {
+                        instruction: #instruction.into(),
+                        fields: std::collections::HashMap::from_iter(
+                            da_rs::field_schemas::<#input>()
+                                .into_iter()
+                                .chain(da_rs::field_schemas::<#output>()),
+                        ),
+                    }
+                }
+            }
+
+            impl da_rs::Signature for #name {
+                type Input = #input;
+                type Output = #output;
+
+                #[inline(always)]
+                fn instruction(&self) -> &str {
+                    &self.instruction
+                }
+
+                #[inline]
+                fn input_fields(&self) -> &[da_rs::Field] {
+                    <Self::Input as da_rs::Model>::fields()
+                }
+
+                #[inline]
+                fn output_fields(&self) -> &[da_rs::Field] {
+                    <Self::Output as da_rs::Model>::fields()
+                }
+
+                #[inline]
+                fn field(&self, name: &str) -> Option<&da_rs::schemars::Schema> {
+                    self.fields.get(name)
+                }
+            }
+        });
+    }
+}
+
+/// Signatures only take doc comments, used as the instruction.
+fn check_signature_attributes(attrs: &[Attribute]) -> syn::Result<()> {
+    match attrs.iter().find(|attr| !attr.path().is_ident("doc")) {
+        Some(attr) => Err(syn::Error::new_spanned(
+            attr,
+            "Unknown attribute on signature, expected doc",
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Arguments of the `#[field(...)]` attribute of a generated model field.
+fn field_args(
+    desc: &Option<String>,
+    constraints: &[Constraint],
+    default: &Option<Expr>,
+) -> Vec<TokenStream> {
+    let mut args = vec![];
+    if let Some(desc) = desc {
+        let desc = LitStr::new(desc, Span::call_site());
+        args.push(quote!(desc = #desc));
+    }
+    args.extend(constraints.iter().map(Constraint::to_field_arg));
+    if let Some(default) = default {
+        args.push(quote!(default = #default));
+    }
+    args
+}
@@ -1,35 +1,341 @@
-use syn::{Attribute, Expr, Lit, MetaNameValue, spanned::Spanned};
-
-pub(crate) fn parse_desc(attr: &Attribute) -> syn::Result<Option<String>> {
-    match attr.parse_args::<MetaNameValue>() {
-        Ok(args) => match args.path.get_ident() {
-            Some(ident) => {
-                if ident == "desc" {
-                    match args.value {
-                        Expr::Lit(lit) => match lit.lit {
-                            Lit::Str(str) => Ok(Some(str.value())),
-                            _ => {
-                                return Err(syn::Error::new(lit.span(), "Expected string literal"));
-                            }
-                        },
-                        _ => {
-                            return Err(syn::Error::new(
-                                args.value.span(),
-                                "Expected string literal",
-                            ));
+use std::collections::HashSet;
+
+use proc_macro2::{TokenStream, TokenTree};
+use quote::{ToTokens, quote};
+use syn::{
+    Attribute, Expr, GenericParam, Generics, Ident, Lit, LitStr, Meta, MetaList, Token, Type,
+    ext::IdentExt, parse::ParseStream, parse_quote, punctuated::Punctuated, spanned::Spanned,
+};
+
+/// Arguments of the `#[input(...)]`, `#[output(...)]` and `#[field(...)]` attributes.
+#[derive(Default)]
+pub(crate) struct FieldArgs {
+    pub desc: Option<String>,
+    pub constraints: Vec<Constraint>,
+    /// `default = ..`, the value of the field when it is missing.
+    pub default: Option<Expr>,
+}
+
+/// Value constraint on a field, validated after the output is parsed.
+#[derive(Clone)]
+pub(crate) enum Constraint {
+    /// `range(min = .., max = ..)` on numbers.
+    Range(MetaList),
+    /// `length(min = .., max = ..)` on strings and lists.
+    Length(MetaList),
+    /// `regex = ".."` on strings.
+    Regex(LitStr),
+    /// `one_of = [..]` on scalars.
+    OneOf(Expr),
+}
+
+impl Constraint {
+    /// Tokens of the constraint as an argument of the `#[field(...)]` attribute.
+    pub(crate) fn to_field_arg(&self) -> TokenStream {
+        match self {
+            Constraint::Range(list) | Constraint::Length(list) => quote!(#list),
+            Constraint::Regex(regex) => quote!(regex = #regex),
+            Constraint::OneOf(values) => quote!(one_of = #values),
+        }
+    }
+
+    /// Tokens of the `#[schemars(...)]` attribute that adds the constraint to the schema.
+    pub(crate) fn to_schemars_attr(&self) -> TokenStream {
+        match self {
+            Constraint::Range(list) | Constraint::Length(list) => quote!(#[schemars(#list)]),
+            Constraint::Regex(regex) => quote!(#[schemars(regex(pattern = #regex))]),
+            Constraint::OneOf(values) => quote!(#[schemars(extend("enum" = #values))]),
+        }
+    }
+}
+
+/// Attributes passed through to the generated models, enums and their fields.
+const PASS_THROUGH_ATTRIBUTES: &[&str] = &["serde", "schemars", "doc"];
+
+pub(crate) fn is_pass_through(attr: &Attribute) -> bool {
+    PASS_THROUGH_ATTRIBUTES
+        .iter()
+        .any(|name| attr.path().is_ident(name))
+}
+
+/// Checks that all attributes are passed through, `item` names the annotated item in errors.
+pub(crate) fn check_pass_through(attrs: &[Attribute], item: &str) -> syn::Result<()> {
+    match attrs.iter().find(|attr| !is_pass_through(attr)) {
+        Some(attr) => Err(syn::Error::new_spanned(
+            attr,
+            format!("Unknown attribute on {item}, expected one of serde, schemars or doc"),
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Name of a field in JSON, as serialized by serde: `#[serde(rename = "..")]`
+/// or the identifier without the `r#` prefix, renamed by the `rename_all` rule
+/// of the container. Fields skipped or flattened by serde have no name of their
+/// own and are rejected.
+pub(crate) fn field_name(
+    ident: &Ident,
+    attrs: &[Attribute],
+    rename_all: Option<&LitStr>,
+) -> syn::Result<String> {
+    let mut name = ident.unraw().to_string();
+    if let Some(rule) = rename_all {
+        name = apply_rename_rule(rule, &name)?;
+    }
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("serde")) {
+        let metas = attr.parse_args_with(Punctuated::<Meta, Token![,]>::parse_terminated)?;
+        for meta in metas {
+            match meta {
+                Meta::NameValue(nv) if nv.path.is_ident("rename") => {
+                    name = parse_lit_str(&nv.value)?.value();
+                }
+                Meta::Path(path) if path.is_ident("skip") || path.is_ident("flatten") => {
+                    let attr = path.to_token_stream();
+                    return Err(syn::Error::new_spanned(
+                        path,
+                        format!("`#[serde({attr})]` is not supported on field {ident}"),
+                    ));
+                }
+                _ => {}
+            }
+        }
+    }
+    Ok(name)
+}
+
+/// The `#[serde(rename_all = "..")]` rule of a container, or its `serialize` rule.
+pub(crate) fn rename_all(attrs: &[Attribute]) -> syn::Result<Option<LitStr>> {
+    let mut rule = None;
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("serde")) {
+        let metas = attr.parse_args_with(Punctuated::<Meta, Token![,]>::parse_terminated)?;
+        for meta in metas {
+            match meta {
+                Meta::NameValue(nv) if nv.path.is_ident("rename_all") => {
+                    rule = Some(parse_lit_str(&nv.value)?);
+                }
+                Meta::List(list) if list.path.is_ident("rename_all") => {
+                    let rules =
+                        list.parse_args_with(Punctuated::<Meta, Token![,]>::parse_terminated)?;
+                    for rule_meta in rules {
+                        if let Meta::NameValue(nv) = rule_meta
+                            && nv.path.is_ident("serialize")
+                        {
+                            rule = Some(parse_lit_str(&nv.value)?);
                         }
                     }
-                } else {
-                    return Err(syn::Error::new(
-                        attr.span(),
-                        format!("Invalid parameter: {ident}"),
-                    ));
                 }
+                _ => {}
+            }
+        }
+    }
+    Ok(rule)
+}
+
+/// Rename a snake case field name like serde's `rename_all` rules.
+fn apply_rename_rule(rule: &LitStr, field: &str) -> syn::Result<String> {
+    let pascal = || {
+        let mut buf = String::new();
+        let mut capitalize = true;
+        for c in field.chars() {
+            if c == '_' {
+                capitalize = true;
+            } else if capitalize {
+                buf.push(c.to_ascii_uppercase());
+                capitalize = false;
+            } else {
+                buf.push(c);
             }
-            None => {
-                return Err(syn::Error::new(attr.span(), "Missing attribute name"));
+        }
+        buf
+    };
+    let name = match rule.value().as_str() {
+        "lowercase" | "snake_case" => field.to_string(),
+        "UPPERCASE" | "SCREAMING_SNAKE_CASE" => field.to_ascii_uppercase(),
+        "PascalCase" => pascal(),
+        "camelCase" => {
+            let pascal = pascal();
+            let mut chars = pascal.chars();
+            match chars.next() {
+                Some(first) => first.to_ascii_lowercase().to_string() + chars.as_str(),
+                None => pascal,
             }
+        }
+        "kebab-case" => field.replace('_', "-"),
+        "SCREAMING-KEBAB-CASE" => field.to_ascii_uppercase().replace('_', "-"),
+        _ => return Err(syn::Error::new(rule.span(), "Unknown rename_all rule")),
+    };
+    Ok(name)
+}
+
+/// Checks that the field name is not reserved nor used by another field.
+pub(crate) fn check_field_name(
+    names: &mut HashSet<String>,
+    name: &str,
+    ident: &Ident,
+) -> syn::Result<()> {
+    if name.starts_with("__") {
+        return Err(syn::Error::new(
+            ident.span(),
+            format!(
+                "Field name `{name}` is reserved, names starting with `__` are used by generated code"
+            ),
+        ));
+    }
+    if !names.insert(name.to_string()) {
+        return Err(syn::Error::new(
+            ident.span(),
+            format!("Duplicate field name `{name}`"),
+        ));
+    }
+    Ok(())
+}
+
+pub(crate) fn parse_field_args(attr: &Attribute) -> syn::Result<FieldArgs> {
+    let metas = match &attr.meta {
+        Meta::Path(_) => return Ok(FieldArgs::default()),
+        Meta::List(list) => {
+            list.parse_args_with(Punctuated::<Meta, Token![,]>::parse_terminated)?
+        }
+        Meta::NameValue(nv) => {
+            return Err(syn::Error::new(nv.span(), "Expected attribute arguments"));
+        }
+    };
+
+    let mut args = FieldArgs::default();
+    for meta in metas {
+        let Some(ident) = meta.path().get_ident() else {
+            return Err(syn::Error::new(meta.span(), "Missing attribute name"));
+        };
+
+        match (ident.to_string().as_str(), &meta) {
+            ("desc", Meta::NameValue(nv)) => args.desc = Some(parse_lit_str(&nv.value)?.value()),
+            ("range", Meta::List(list)) => args.constraints.push(Constraint::Range(list.clone())),
+            ("length", Meta::List(list)) => args.constraints.push(Constraint::Length(list.clone())),
+            ("regex", Meta::NameValue(nv)) => {
+                let regex = parse_lit_str(&nv.value)?;
+                args.constraints.push(Constraint::Regex(regex));
+            }
+            ("default", Meta::NameValue(nv)) => args.default = Some(nv.value.clone()),
+            ("one_of", Meta::NameValue(nv)) => match &nv.value {
+                Expr::Array(_) => args.constraints.push(Constraint::OneOf(nv.value.clone())),
+                _ => return Err(syn::Error::new(nv.value.span(), "Expected array of values")),
+            },
+            _ => {
+                return Err(syn::Error::new(
+                    meta.span(),
+                    format!("Invalid parameter: {ident}"),
+                ));
+            }
+        }
+    }
+
+    Ok(args)
+}
+
+fn parse_lit_str(expr: &Expr) -> syn::Result<LitStr> {
+    match expr {
+        Expr::Lit(lit) => match &lit.lit {
+            Lit::Str(str) => Ok(str.clone()),
+            _ => Err(syn::Error::new(lit.span(), "Expected string literal")),
         },
-        Err(_) => Ok(None),
+        _ => Err(syn::Error::new(expr.span(), "Expected string literal")),
+    }
+}
+
+/// Parse the generics of a model or signature struct, including the where clause.
+/// Only type parameters are supported, as models are owned (`DeserializeOwned + 'static`).
+pub(crate) fn parse_generics(input: ParseStream) -> syn::Result<Generics> {
+    let mut generics: Generics = input.parse()?;
+    generics.where_clause = input.parse()?;
+    for param in &generics.params {
+        match param {
+            GenericParam::Type(_) => {}
+            GenericParam::Lifetime(_) => {
+                return Err(syn::Error::new_spanned(
+                    param,
+                    "Lifetime parameters are not supported, models must be owned",
+                ));
+            }
+            GenericParam::Const(_) => {
+                return Err(syn::Error::new_spanned(
+                    param,
+                    "Const parameters are not supported",
+                ));
+            }
+        }
+    }
+    Ok(generics)
+}
+
+/// Generics restricted to the type parameters used by the given types, with the
+/// where predicates that apply to them, e.g. for the input model of a signature
+/// whose type parameter is only used by outputs.
+pub(crate) fn used_generics<'a>(
+    generics: &Generics,
+    types: impl IntoIterator<Item = &'a Type>,
+) -> Generics {
+    let types = types
+        .into_iter()
+        .map(ToTokens::to_token_stream)
+        .collect::<Vec<_>>();
+    let is_used = |ident: &Ident| types.iter().any(|ty| mentions(ty.clone(), ident));
+    let unused = type_params(generics)
+        .filter(|ident| !is_used(ident))
+        .cloned()
+        .collect::<Vec<_>>();
+
+    let mut used = generics.clone();
+    used.params = generics
+        .params
+        .iter()
+        .filter(|param| match param {
+            GenericParam::Type(param) => !unused.contains(&param.ident),
+            _ => true,
+        })
+        .cloned()
+        .collect();
+    if let Some(where_clause) = &mut used.where_clause {
+        where_clause.predicates = where_clause
+            .predicates
+            .iter()
+            .filter(|pred| {
+                let tokens = pred.to_token_stream();
+                !unused.iter().any(|ident| mentions(tokens.clone(), ident))
+            })
+            .cloned()
+            .collect();
     }
+    used
+}
+
+/// Generics with the bounds of `da_rs::Model` on every type parameter.
+pub(crate) fn model_generics(generics: &Generics) -> Generics {
+    let mut bounded = generics.clone();
+    let params = type_params(generics).cloned().collect::<Vec<_>>();
+    let where_clause = bounded.make_where_clause();
+    for param in params {
+        where_clause.predicates.push(parse_quote! {
+            #param: ::core::fmt::Debug
+                + da_rs::serde::Serialize
+                + da_rs::serde::de::DeserializeOwned
+                + da_rs::schemars::JsonSchema
+                + ::core::marker::Send
+                + ::core::marker::Sync
+                + 'static
+        });
+    }
+    bounded
+}
+
+pub(crate) fn type_params(generics: &Generics) -> impl Iterator<Item = &Ident> {
+    generics.type_params().map(|param| &param.ident)
+}
+
+/// Returns whether the tokens mention the identifier.
+fn mentions(tokens: TokenStream, ident: &Ident) -> bool {
+    tokens.into_iter().any(|token| match token {
+        TokenTree::Ident(i) => &i == ident,
+        TokenTree::Group(group) => mentions(group.stream(), ident),
+        _ => false,
+    })
 }
@@ -0,0 +1,77 @@
+use serde_json::Value;
+
+use crate::lm::MessageContent;
+use crate::media;
+
+/// Builds the content of a message from text and serialized input values,
+/// turning media placeholders into their own parts.
+pub(crate) struct ContentBuilder<'a> {
+    media: &'a [MessageContent],
+    parts: Vec<MessageContent>,
+    text: String,
+}
+
+impl<'a> ContentBuilder<'a> {
+    /// `media` are the parts returned by [`media::serialize`] for the values.
+    pub(crate) fn new(media: &'a [MessageContent]) -> Self {
+        Self {
+            media,
+            parts: vec![],
+            text: String::new(),
+        }
+    }
+
+    pub(crate) fn push_str(&mut self, text: &str) {
+        self.text.push_str(text);
+    }
+
+    /// Write `value` as compact JSON, with media as separate parts.
+    pub(crate) fn push_value(&mut self, value: &Value) {
+        if let Some(part) = media::placeholder(value).and_then(|i| self.media.get(i)) {
+            // Media rendered as text (e.g. document pages) stays in the text part
+            if let MessageContent::Text { text } = part {
+                self.text.push_str(&Value::from(text.as_str()).to_string());
+                return;
+            }
+            if !self.text.is_empty() {
+                let text = std::mem::take(&mut self.text);
+                self.parts.push(MessageContent::Text { text });
+            }
+            self.parts.push(part.clone());
+            return;
+        }
+
+        match value {
+            Value::Array(items) => {
+                self.text.push('[');
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        self.text.push(',');
+                    }
+                    self.push_value(item);
+                }
+                self.text.push(']');
+            }
+            Value::Object(kv) => {
+                self.text.push('{');
+                for (i, (key, value)) in kv.iter().enumerate() {
+                    if i > 0 {
+                        self.text.push(',');
+                    }
+                    self.text.push_str(&Value::from(key.as_str()).to_string());
+                    self.text.push(':');
+                    self.push_value(value);
+                }
+                self.text.push('}');
+            }
+            value => self.text.push_str(&value.to_string()),
+        }
+    }
+
+    pub(crate) fn finish(mut self) -> Vec<MessageContent> {
+        if !self.text.is_empty() || self.parts.is_empty() {
+            self.parts.push(MessageContent::Text { text: self.text });
+        }
+        self.parts
+    }
+}
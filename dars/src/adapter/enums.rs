@@ -0,0 +1,138 @@
+use schemars::Schema;
+use serde_json::Value;
+
+/// Returns the allowed values of an enum schema: an `enum` keyword or a
+/// `oneOf`/`anyOf` of `const` values (unit enums with documented variants).
+pub(crate) fn choices(schema: &Value) -> Option<Vec<Value>> {
+    if let Some(Value::Array(values)) = schema.get("enum") {
+        return Some(values.clone());
+    }
+
+    let branches = schema
+        .get("oneOf")
+        .or_else(|| schema.get("anyOf"))?
+        .as_array()?;
+    branches
+        .iter()
+        .map(|branch| branch.get("const").cloned())
+        .collect()
+}
+
+/// Replace the strings in `value` that match an enum choice of `schema` up to
+/// case and whitespace with the choice, e.g. ` Positive ` with `positive`.
+pub(crate) fn normalize(schema: &Schema, value: &mut Value) {
+    let root = schema.as_value();
+    normalize_value(root, root, value, 0);
+}
+
+/// Maximum depth of `$ref` resolution, to stop on recursive schemas.
+const MAX_DEPTH: usize = 32;
+
+fn normalize_value(root: &Value, schema: &Value, value: &mut Value, depth: usize) {
+    if depth > MAX_DEPTH {
+        return;
+    }
+
+    if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+        if let Some(schema) = resolve(root, reference) {
+            normalize_value(root, schema, value, depth + 1);
+        }
+        return;
+    }
+
+    if let Some(choices) = choices(schema) {
+        if let Value::String(s) = value
+            && let Some(choice) = choices
+                .iter()
+                .find(|c| c.as_str().is_some_and(|c| same(c, s)))
+        {
+            *value = choice.clone();
+        }
+        return;
+    }
+
+    // e.g. `Option<T>`
+    for keyword in ["anyOf", "oneOf", "allOf"] {
+        if let Some(Value::Array(branches)) = schema.get(keyword) {
+            for branch in branches {
+                normalize_value(root, branch, value, depth + 1);
+            }
+        }
+    }
+
+    match value {
+        Value::Object(kv) => {
+            let Some(Value::Object(properties)) = schema.get("properties") else {
+                return;
+            };
+            for (name, value) in kv.iter_mut() {
+                if let Some(schema) = properties.get(name) {
+                    normalize_value(root, schema, value, depth + 1);
+                }
+            }
+        }
+        Value::Array(items) => {
+            let Some(schema) = schema.get("items") else {
+                return;
+            };
+            for item in items {
+                normalize_value(root, schema, item, depth + 1);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Resolve a local reference such as `#/$defs/Sentiment`.
+fn resolve<'a>(root: &'a Value, reference: &str) -> Option<&'a Value> {
+    root.pointer(reference.strip_prefix('#')?)
+}
+
+/// Compare ignoring case and whitespace.
+fn same(a: &str, b: &str) -> bool {
+    let key = |s: &str| {
+        s.chars()
+            .filter(|c| !c.is_whitespace())
+            .flat_map(char::to_lowercase)
+            .collect::<String>()
+    };
+    key(a) == key(b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use schemars::json_schema;
+    use serde_json::json;
+
+    #[test]
+    fn test_normalize() {
+        let schema = json_schema!({
+            "type": "object",
+            "properties": {
+                "label": {"$ref": "#/$defs/Label"},
+                "labels": {"type": "array", "items": {"$ref": "#/$defs/Label"}},
+                "maybe": {"anyOf": [{"$ref": "#/$defs/Tone"}, {"type": "null"}]},
+            },
+            "$defs": {
+                "Label": {"type": "string", "enum": ["Positive", "very negative"]},
+                "Tone": {"oneOf": [{"const": "calm"}, {"const": "angry"}]},
+            },
+        });
+
+        let mut value = json!({
+            "label": " positive ",
+            "labels": ["VeryNegative", "POSITIVE", "neutral"],
+            "maybe": "Angry",
+        });
+        normalize(&schema, &mut value);
+        assert_eq!(
+            value,
+            json!({
+                "label": "Positive",
+                "labels": ["very negative", "Positive", "neutral"],
+                "maybe": "angry",
+            })
+        );
+    }
+}
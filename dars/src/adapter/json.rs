@@ -1,13 +1,13 @@
-use regex::Regex;
-use schemars::{Schema, schema_for};
-use serde::de::DeserializeOwned;
-use serde_json::Value;
+use schemars::Schema;
+use serde_json::{Map, Value};
 use tracing::{error, warn};
 
-use super::Adapter;
+use super::{Adapter, content::ContentBuilder, enums, partial_json, types::TypeRenderer};
 use crate::{
-    Error, Signature,
+    Error, Field, History, Image, Signature,
     lm::{Message, MessageContent},
+    media,
+    validate::{CONSTRAINT_KEYWORDS, validate},
 };
 
 pub struct JsonAdapter<S: Signature> {
@@ -16,31 +16,154 @@ pub struct JsonAdapter<S: Signature> {
 
 impl<S: Signature> Adapter<S> for JsonAdapter<S> {
     fn format(&self, input: S::Input) -> Result<(Vec<Message>, Option<Schema>), Error> {
-        let messages = vec![self.format_system_message(), self.format_input(input)?];
-        Ok((messages, Some(schema_for!(S::Output))))
+        let (mut input, mut media) = match media::serialize(&input)? {
+            (Value::Object(kv), media) => (kv, media),
+            _ => unreachable!(),
+        };
+
+        // Images of untyped inputs (e.g. of a `DynamicSignature`) are plain JSON
+        for f in self.signature.input_fields() {
+            let Some(value) = input.get_mut(f.name) else {
+                continue;
+            };
+            if media::placeholder(value).is_none()
+                && self.signature.field(f.name).is_some_and(Image::is_image)
+            {
+                let image: Image = serde_json::from_value(value.take())?;
+                media.push(MessageContent::Image { url: image.url });
+                *value = media::placeholder_value(media.len() - 1);
+            }
+        }
+
+        // Expand prior turns into user/assistant messages
+        let mut messages = vec![self.format_system_message()?];
+        for f in self.signature.input_fields() {
+            if !self.is_history(f) {
+                continue;
+            }
+            if let Some(history) = input.get(f.name) {
+                let history: History = serde_json::from_value(history.clone())?;
+                for turn in history.turns {
+                    messages.push(self.format_input(&turn.input, &turn.media));
+                    messages.push(self.format_output(&turn.output));
+                }
+            }
+        }
+        messages.push(self.format_input(&input, &media));
+
+        Ok((messages, Some(self.output_schema())))
     }
 
     fn parse(&self, output: String) -> Result<S::Output, Error> {
-        // Strip ```json``` quotes from the content (if present)
-        let output = output.strip_prefix("```json").unwrap_or(&output);
-        let output = output.strip_suffix("```").unwrap_or(&output);
+        let output = strip_fences(&output);
 
         // Try to parse `output` as a JSON object directly.
-        match serde_json::from_str(&output) {
-            Ok(value) => Ok(value),
+        let schema = self.signature.output_schema();
+        let strict = serde_json::from_str(output).and_then(|mut value| {
+            enums::normalize(&schema, &mut value);
+            serde_json::from_value(value)
+        });
+        let value: S::Output = match strict {
+            Ok(value) => value,
             Err(e) => {
                 warn!("Failed to parse strict JSON: {output:?}: {e:?}");
 
                 // If strict JSON parsing fails, try speculative parsing.
-                match try_speculative_json::<S::Output>(&output) {
-                    Some(value) => Ok(value),
+                match partial_json::parse::<S::Output>(output, &schema) {
+                    Some(value) => value,
                     None => {
                         error!("Failed to parse speculative JSON: {output:?}");
-                        return Err(Error::SerdeJson(e));
+                        return Err(self.missing_fields(output).unwrap_or(Error::SerdeJson(e)));
                     }
                 }
             }
+        };
+
+        self.validate(value)
+    }
+
+    fn format_images(&self, messages: &[Message]) -> Option<(Vec<Message>, usize)> {
+        let fields = self.image_fields();
+        if fields.is_empty() {
+            return None;
+        }
+
+        let mut buf = format!("Generate {} image(s), in this order:\n", fields.len());
+        for (i, f) in fields.iter().enumerate() {
+            buf += &format!(
+                "{}. `{}`: {}\n",
+                i + 1,
+                f.name,
+                f.description.unwrap_or_default()
+            );
+        }
+        buf += "\nThe images are based on the input fields";
+        if !self.signature.instruction().is_empty() {
+            buf += ", with the objective:\n";
+            buf += self.signature.instruction().trim();
+        }
+
+        // Keep the input messages, replacing the instructions for the JSON output
+        let mut image_messages = vec![Message::System { instruction: buf }];
+        image_messages.extend(
+            messages
+                .iter()
+                .filter(|m| !matches!(m, Message::System { .. }))
+                .cloned(),
+        );
+        Some((image_messages, fields.len()))
+    }
+
+    fn parse_with_images(
+        &self,
+        output: String,
+        images: Vec<MessageContent>,
+    ) -> Result<S::Output, Error> {
+        let fields = self.image_fields();
+        if fields.is_empty() {
+            return self.parse(output);
+        }
+        if images.len() != fields.len() {
+            return Err(Error::ModelCall(format!(
+                "expected {} images, got {}",
+                fields.len(),
+                images.len()
+            )));
+        }
+
+        // Parse the text fields, if any
+        let schema = self.signature.output_schema();
+        let output = strip_fences(&output);
+        let mut kv = if output.trim().is_empty() {
+            Map::new()
+        } else {
+            match serde_json::from_str(output) {
+                Ok(kv) => kv,
+                Err(e) => {
+                    warn!("Failed to parse strict JSON: {output:?}: {e:?}");
+                    partial_json::parse::<Map<String, Value>>(output, &schema).ok_or(e)?
+                }
+            }
+        };
+
+        // Fill the image fields in order
+        for (f, image) in fields.iter().zip(images) {
+            let MessageContent::Image { url } = image else {
+                return Err(Error::ModelCall(format!(
+                    "expected an image for `{}`, got {image}",
+                    f.name
+                )));
+            };
+            kv.insert(f.name.to_string(), Value::String(url));
+        }
+
+        let mut value = Value::Object(kv);
+        enums::normalize(&schema, &mut value);
+        let missing = required_missing(&schema, &value);
+        if !missing.is_empty() {
+            return Err(Error::MissingFields(missing));
         }
+        self.validate(serde_json::from_value(value)?)
     }
 }
 
@@ -49,52 +172,60 @@ impl<S: Signature> JsonAdapter<S> {
         Self { signature }
     }
 
-    fn format_system_message(&self) -> Message {
+    fn format_system_message(&self) -> Result<Message, Error> {
         let mut buf = String::new();
+        let mut types = TypeRenderer::new();
         // Input fields
         buf += "Your input fields are:\n";
-        for (i, f) in self.signature.input_fields().iter().enumerate() {
-            let fty = self
-                .signature
-                .field(f.name)
-                .expect("Field not found in schema")
-                .as_value();
-            buf += &format!("{}. `{}` (", i + 1, f.name);
-            fmt_type(fty, &mut buf);
-            buf += &format!("): {}\n", f.description.unwrap_or_default());
+        for (i, f) in self.input_fields().iter().enumerate() {
+            let fty = self.field_schema(f)?;
+            buf += &format!("{}. `{}` ({}): ", i + 1, f.name, types.render(fty));
+            buf += &format!("{}\n", f.description.unwrap_or_default());
         }
 
-        // Output fields
+        // Output fields, marking the ones that may be omitted
+        let output_schema = self.signature.output_schema();
+        let required = output_schema
+            .get("required")
+            .and_then(Value::as_array)
+            .cloned()
+            .unwrap_or_default();
         buf += "\nYour output fields are:\n";
-        for (i, f) in self.signature.output_fields().iter().enumerate() {
-            let fty = self
-                .signature
-                .field(f.name)
-                .expect("Field not found in schema")
-                .as_value();
-            buf += &format!("{}. `{}` (", i + 1, f.name);
-            fmt_type(fty, &mut buf);
-            buf += &format!("): {}\n", f.description.unwrap_or_default());
+        for (i, f) in self.output_fields().iter().enumerate() {
+            let fty = self.field_schema(f)?;
+            let mut ty = types.render(fty);
+            let default = output_schema
+                .get("properties")
+                .and_then(|p| p.get(f.name))
+                .and_then(|p| p.get("default"));
+            if let Some(default) = default {
+                ty += &format!(", default: {default}");
+            } else if !required.iter().any(|r| r == f.name) {
+                ty += ", optional";
+            }
+            buf += &format!("{}. `{}` ({ty}): ", i + 1, f.name);
+            buf += &format!("{}\n", f.description.unwrap_or_default());
         }
         buf += "All interactions will be structured in the following way, with the appropriate values filled in.\n";
 
         // Input structure
         buf += "\nInputs will have the following structure:\n";
-        for f in self.signature.input_fields() {
+        for f in self.input_fields() {
             buf += &format!("\n[[ ## {} ## ]]\n{{{}}}\n", f.name, f.name)
         }
 
         // Output structure
         buf += "\nOutputs will be a JSON object with the following fields.\n";
         buf += "{\n";
-        for (i, f) in self.signature.output_fields().iter().enumerate() {
+        for (i, f) in self.output_fields().iter().enumerate() {
             buf += &format!("\t\"{}\": \"{{{}}}", f.name, f.name);
             if let Some(schema) = self.signature.field(f.name) {
+                let schema = with_constraints(schema, &output_schema, f.name);
                 buf += " # note: the value you produce must adhere to the JSON schema: ";
-                buf += &serde_json::to_string(schema).unwrap();
+                buf += &serde_json::to_string(&schema).unwrap();
             }
             buf += "\"";
-            if i + 1 < self.signature.output_fields().len() {
+            if i + 1 < self.output_fields().len() {
                 buf += ",\n";
             }
         }
@@ -104,22 +235,23 @@ impl<S: Signature> JsonAdapter<S> {
         buf += "\nIn adhering to this structure, your objective is:\n";
         if self.signature.instruction().is_empty() {
             buf += "Given the fields ";
-            for (i, f) in self.signature.input_fields().iter().enumerate() {
+            let input_fields = self.input_fields();
+            for (i, f) in input_fields.iter().enumerate() {
                 buf += &format!("`{}`", f.name);
-                if i + 1 < self.signature.input_fields().len() {
+                if i + 1 < input_fields.len() {
                     buf += ", ";
                 }
             }
             buf += ", produce the fields ";
-            for (i, f) in self.signature.output_fields().iter().enumerate() {
+            for (i, f) in self.output_fields().iter().enumerate() {
                 buf += &format!("`{}`", f.name);
-                if i + 1 < self.signature.output_fields().len() {
+                if i + 1 < self.output_fields().len() {
                     buf += ", ";
                 }
             }
             buf += ".";
         } else {
-            buf += &self.signature.instruction().trim();
+            buf += self.signature.instruction().trim();
         }
 
         // Add JSON formatting instructions
@@ -127,131 +259,189 @@ impl<S: Signature> JsonAdapter<S> {
         buf += "Do not include any text before or after the JSON.\n";
         buf += "Do not use markdown.\n";
 
-        Message::System { instruction: buf }
+        Ok(Message::System { instruction: buf })
     }
 
-    fn format_input(&self, input: S::Input) -> Result<Message, Error> {
-        match serde_json::to_value(&input)? {
-            Value::Object(kv) => {
-                let mut buf = String::new();
-                for (i, f) in self.signature.input_fields().iter().enumerate() {
-                    // Header
-                    buf.push_str("[[ ## ");
-                    buf.push_str(f.name);
-                    buf.push_str(" ## ]]");
-                    // Value
-                    if let Some(value) = kv.get(f.name) {
-                        buf.push('\n');
-                        buf.push_str(&value.to_string());
-                    }
-                    // Separator
-                    if i + 1 < self.signature.input_fields().len() {
-                        buf += "\n\n";
-                    }
-                }
+    /// Returns the schema of a field, which the signature must have.
+    fn field_schema(&self, field: &Field) -> Result<&Schema, Error> {
+        self.signature.field(field.name).ok_or_else(|| {
+            Error::InvalidArgument(format!(
+                "field `{}` not found in the signature schemas",
+                field.name
+            ))
+        })
+    }
 
-                Ok(Message::User {
-                    content: parse_content(buf),
-                })
+    /// Format the input fields, with `media` referenced by placeholders in the
+    /// values as separate parts.
+    fn format_input(&self, input: &Map<String, Value>, media: &[MessageContent]) -> Message {
+        let fields = self.input_fields();
+        let mut content = ContentBuilder::new(media);
+        for (i, f) in fields.iter().enumerate() {
+            // Header
+            content.push_str("[[ ## ");
+            content.push_str(f.name);
+            content.push_str(" ## ]]");
+            // Value
+            if let Some(value) = input.get(f.name) {
+                content.push_str("\n");
+                content.push_value(value);
             }
-            _ => unreachable!(),
+            // Separator
+            if i + 1 < fields.len() {
+                content.push_str("\n\n");
+            }
+        }
+
+        Message::User {
+            content: content.finish(),
+        }
+    }
+
+    /// Format the output of a prior turn as the JSON object the LM is asked to produce.
+    fn format_output(&self, output: &Map<String, Value>) -> Message {
+        let fields = self
+            .signature
+            .output_fields()
+            .iter()
+            .filter_map(|f| Some(format!("\"{}\": {}", f.name, output.get(f.name)?)))
+            .collect::<Vec<_>>();
+
+        Message::Assistant {
+            content: MessageContent::Text {
+                text: format!("{{{}}}", fields.join(", ")),
+            },
         }
     }
-}
 
-/// Try to parse the output as a JSON object surrounded by gibberish,
-/// returning the first successful parse.
-/// Uses greedy regex matching to find nested JSON objects.
-fn try_speculative_json<T: DeserializeOwned>(output: &str) -> Option<T> {
-    // Greedy regex to match JSON objects including nested ones
-    let re = Regex::new(r"(?s)\{.*\}").unwrap();
+    /// Returns whether the signature has outputs other than images, which need
+    /// an LM call with the JSON schema of [`Adapter::format`].
+    pub(crate) fn has_text_outputs(&self) -> bool {
+        !self.output_fields().is_empty()
+    }
+
+    /// Returns [`Error::MissingFields`] if the JSON object in the output lacks
+    /// required fields.
+    fn missing_fields(&self, output: &str) -> Option<Error> {
+        let value = serde_json::from_str::<Value>(output)
+            .ok()
+            .filter(Value::is_object)
+            .or_else(|| {
+                let candidates = partial_json::candidates(output);
+                candidates
+                    .into_iter()
+                    .find(|c| c.depth == 0)
+                    .map(|c| c.value)
+            })?;
+        let missing = required_missing(&self.signature.output_schema(), &value);
+        (!missing.is_empty()).then_some(Error::MissingFields(missing))
+    }
 
-    for mat in re.find_iter(output) {
-        if let Ok(value) = serde_json::from_str::<T>(mat.as_str()) {
-            return Some(value);
+    /// Validate the field constraints of the output. Required fields are checked
+    /// for outputs that deserialize from any object, e.g. of [`DynamicSignature`](crate::DynamicSignature).
+    fn validate(&self, value: S::Output) -> Result<S::Output, Error> {
+        let schema = self.signature.output_schema();
+        let json = serde_json::to_value(&value)?;
+        let missing = required_missing(&schema, &json);
+        if !missing.is_empty() {
+            return Err(Error::MissingFields(missing));
         }
+        let violations = validate(&schema, &json);
+        if !violations.is_empty() {
+            warn!("Output violates schema constraints: {violations:?}");
+            return Err(Error::Validation(violations));
+        }
+        Ok(value)
     }
 
-    None
-}
+    /// Returns the schema of the output without the [`Image`] fields, which are
+    /// generated separately.
+    fn output_schema(&self) -> Schema {
+        let mut schema = self.signature.output_schema();
+        for f in self.image_fields() {
+            if let Some(properties) = schema.get_mut("properties").and_then(Value::as_object_mut) {
+                properties.remove(f.name);
+            }
+            if let Some(required) = schema.get_mut("required").and_then(Value::as_array_mut) {
+                required.retain(|name| name != f.name);
+            }
+        }
+        schema
+    }
 
-fn parse_content(buf: String) -> Vec<MessageContent> {
-    let re = Regex::new(r"<dars-img>(.*?)</dars-img>").unwrap();
+    /// Returns the output fields except [`Image`] fields.
+    fn output_fields(&self) -> Vec<&Field> {
+        self.signature
+            .output_fields()
+            .iter()
+            .filter(|f| !self.is_image(f))
+            .collect()
+    }
 
-    // Find image tags in the serialized input
-    let mut img_pos = vec![];
-    for m in re.find_iter(&buf) {
-        img_pos.push((m.start(), m.end()));
+    fn image_fields(&self) -> Vec<&Field> {
+        self.signature
+            .output_fields()
+            .iter()
+            .filter(|f| self.is_image(f))
+            .collect()
     }
 
-    if img_pos.is_empty() {
-        // No images found, just return the text
-        return vec![MessageContent::Text { text: buf }];
+    fn is_image(&self, field: &Field) -> bool {
+        self.signature
+            .field(field.name)
+            .is_some_and(Image::is_image)
     }
 
-    // Split text into text and images
-    let mut content = vec![];
-    let mut text_start = 0;
-    for (img_start, img_end) in img_pos {
-        if text_start < img_start {
-            content.push(MessageContent::Text {
-                text: buf[text_start..img_start].to_string(),
-            });
-        }
-        content.push(MessageContent::Image {
-            url: buf[(img_start.saturating_add(10))..(img_end.saturating_sub(11))].to_string(),
-        });
-        text_start = img_end;
+    /// Returns the input fields except [`History`] fields, which are formatted as messages.
+    fn input_fields(&self) -> Vec<&Field> {
+        self.signature
+            .input_fields()
+            .iter()
+            .filter(|f| !self.is_history(f))
+            .collect()
     }
-    if text_start < buf.len() {
-        content.push(MessageContent::Text {
-            text: buf[text_start..].to_string(),
-        });
+
+    fn is_history(&self, field: &Field) -> bool {
+        self.signature
+            .field(field.name)
+            .is_some_and(History::is_history)
     }
-    content
 }
 
-fn fmt_type(ty: &Value, buf: &mut String) {
-    if let Some(vty) = ty.get("type") {
-        match vty.as_str().unwrap() {
-            "null" => buf.push_str("null"),
-            "boolean" => buf.push_str("boolean"),
-            "string" => buf.push_str("string"),
-            "integer" => buf.push_str("integer"),
-            "number" => buf.push_str("number"),
-            "array" => {
-                buf.push_str("list[");
-                if let Some(items) = ty.get("items") {
-                    if items.get("type").is_some() {
-                        fmt_type(items, buf);
-                    } else if let Some(tref) = items.get("$ref") {
-                        let ty = tref.as_str().unwrap().split("/").last().unwrap();
-                        buf.push_str(ty);
-                    }
-                }
-                buf.push_str("]");
-            }
-            "object" => {
-                buf.push('{');
-                let properties = ty.get("properties").map(|v| v.as_object()).flatten();
-                if let Some(properties) = properties {
-                    for (i, (name, value)) in properties.iter().enumerate() {
-                        buf.push_str(name);
-                        buf.push_str(": ");
-                        fmt_type(value, buf);
-                        if i + 1 < properties.len() {
-                            buf.push(',');
-                        }
-                    }
-                }
-                buf.push('}');
+/// Returns the required properties of the `schema` missing in the `value` object.
+fn required_missing(schema: &Schema, value: &Value) -> Vec<String> {
+    let Some(Value::Array(required)) = schema.get("required") else {
+        return vec![];
+    };
+    required
+        .iter()
+        .filter_map(Value::as_str)
+        .filter(|name| value.get(name).is_none())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Strip ```json``` quotes from the content (if present).
+fn strip_fences(output: &str) -> &str {
+    let output = output.strip_prefix("```json").unwrap_or(output);
+    output.strip_suffix("```").unwrap_or(output)
+}
+
+/// Copy the constraints of the `name` property in `model` into the field `schema`.
+fn with_constraints(schema: &Schema, model: &Schema, name: &str) -> Schema {
+    let mut schema = schema.clone();
+    let property = model
+        .get("properties")
+        .and_then(|p| p.get(name))
+        .and_then(Value::as_object);
+    if let Some(property) = property {
+        for keyword in CONSTRAINT_KEYWORDS {
+            if let Some(value) = property.get(*keyword) {
+                schema.insert(keyword.to_string(), value.clone());
             }
-            _ => {}
         }
-    } else if let Some(vty) = ty.get("$ref") {
-        let ty = vty.as_str().unwrap().split("/").last().unwrap();
-        buf.push_str(ty);
     }
+    schema
 }
 
 #[cfg(test)]
@@ -266,50 +456,125 @@ mod tests {
     use da_rs::*;
 
     #[test]
-    fn test_parse_content() {
-        let content = parse_content(
-            "this is some<dars-img>https://example.com/image.png</dars-img> text with an image <dars-img>https://example.com/image2.png</dars-img><dars-img>https://example.com/image3.png</dars-img>and some more text".to_string()
-        );
+    fn test_format_images() {
+        #[Model]
+        struct Album {
+            #[field]
+            title: String,
+            #[field]
+            cover: Image,
+        }
 
-        assert_eq!(content.len(), 6);
-        assert_eq!(
-            content[0],
-            MessageContent::Text {
-                text: "this is some".to_string()
-            }
-        );
-        assert_eq!(
-            content[1],
-            MessageContent::Image {
-                url: "https://example.com/image.png".to_string()
-            }
-        );
-        assert_eq!(
-            content[2],
-            MessageContent::Text {
-                text: " text with an image ".to_string()
-            }
-        );
-        assert_eq!(
-            content[3],
-            MessageContent::Image {
-                url: "https://example.com/image2.png".to_string()
-            }
-        );
+        #[Signature]
+        struct TestSignature {
+            #[input]
+            note: String,
+            #[input]
+            photo: Image,
+            #[input]
+            photos: Vec<Image>,
+            #[input]
+            album: Album,
+            #[output]
+            caption: String,
+        }
+
+        let image = |name: &str| Image::from_url(format!("https://example.com/{name}.png"));
+        let part = |name: &str| MessageContent::Image {
+            url: format!("https://example.com/{name}.png"),
+        };
+        let text = |text: &str| MessageContent::Text {
+            text: text.to_string(),
+        };
+
+        let adapter = JsonAdapter::new(TestSignature::new());
+        let (messages, _) = adapter
+            .format(TestSignatureInput {
+                note: "<dars-img>https://example.com/fake.png</dars-img>".to_string(),
+                photo: image("photo"),
+                photos: vec![image("a"), image("b")],
+                album: Album {
+                    title: "Holiday".to_string(),
+                    cover: image("cover"),
+                },
+            })
+            .unwrap();
+
+        let Message::User { content } = &messages[1] else {
+            panic!("expected a user message");
+        };
         assert_eq!(
-            content[4],
-            MessageContent::Image {
-                url: "https://example.com/image3.png".to_string()
-            }
+            content,
+            &vec![
+                text(
+                    "[[ ## note ## ]]\n\"<dars-img>https://example.com/fake.png</dars-img>\"\n\n\
+                    [[ ## photo ## ]]\n"
+                ),
+                part("photo"),
+                text("\n\n[[ ## photos ## ]]\n["),
+                part("a"),
+                text(","),
+                part("b"),
+                text("]\n\n[[ ## album ## ]]\n{\"cover\":"),
+                part("cover"),
+                text(",\"title\":\"Holiday\"}"),
+            ]
         );
+
+        // Outside of adapters images serialize as objects
         assert_eq!(
-            content[5],
-            MessageContent::Text {
-                text: "and some more text".to_string()
-            }
+            serde_json::to_value(image("photo")).unwrap(),
+            serde_json::json!({"url": "https://example.com/photo.png"})
         );
     }
 
+    #[test]
+    fn test_image_outputs() {
+        #[Signature]
+        struct TestSignature {
+            #[input]
+            prompt: String,
+            #[output]
+            caption: String,
+            #[output]
+            picture: Image,
+        }
+
+        let adapter = JsonAdapter::new(TestSignature::new());
+        let (messages, schema) = adapter
+            .format(TestSignatureInput {
+                prompt: "a cat".to_string(),
+            })
+            .unwrap();
+
+        // Image fields are left out of the JSON output
+        let schema = schema.unwrap().to_value();
+        assert!(schema["properties"].get("picture").is_none());
+        assert_eq!(schema["required"], serde_json::json!(["caption"]));
+
+        let (image_messages, n) = adapter.format_images(&messages).unwrap();
+        assert_eq!(n, 1);
+        assert_eq!(image_messages[1], messages[1]);
+
+        let url = "https://example.com/cat.png";
+        let image = MessageContent::Image {
+            url: url.to_string(),
+        };
+        let output = adapter
+            .parse_with_images(
+                "```json{\"caption\": \"A cat\"}```".to_string(),
+                vec![image.clone()],
+            )
+            .unwrap();
+        assert_eq!(output.caption, "A cat");
+        assert_eq!(output.picture, Image::from_url(url));
+
+        let err = adapter
+            .parse_with_images("{\"caption\": \"A cat\"}".to_string(), vec![image; 2])
+            .unwrap_err();
+        assert!(matches!(err, Error::ModelCall(_)));
+    }
+
     #[test]
     fn test_parse_response() {
         #[Signature]
@@ -364,6 +629,14 @@ mod tests {
         "Ivy",
         800
     )]
+    #[case(
+        "First: {\"invalid\": true} Second: {\"name\": \"Frank\", \"value\": 500}",
+        "Frank",
+        500
+    )]
+    #[case("{\"name\": \"Jack {}\", \"value\": 900,} trailing }", "Jack {}", 900)]
+    #[case("{name: \"Kate\", value: 1000}", "Kate", 1000)]
+    #[case("{\"value\": 1100, \"name\": \"Liam", "Liam", 1100)]
     fn test_speculative_json_extraction(
         #[case] input: &str,
         #[case] expected_name: &str,
@@ -399,7 +672,6 @@ mod tests {
     #[case("Text {\"outer\": {\"inner\": \"value\"}} more text")]
     #[case("Text {\"name\": \"test\", \"value\":} more text")]
     #[case("Text {\"name\": \"unclosed string} more text")]
-    #[case("First: {\"invalid\": true} Second: {\"name\": \"Frank\", \"value\": 500}")]
     fn test_speculative_json_extraction_failures(#[case] input: &str) {
         #[Signature]
         struct TestSignature {
@@ -1,8 +1,15 @@
 use schemars::Schema;
 
-use crate::{Error, Signature, lm::Message};
+use crate::{
+    Error, Signature,
+    lm::{Message, MessageContent},
+};
 
+mod content;
+mod enums;
 pub mod json;
+mod partial_json;
+mod types;
 
 pub trait Adapter<S: Signature>: Send + Sync + 'static {
     /// Format the input as a list of chat messages with an optional json schema
@@ -11,4 +18,27 @@ pub trait Adapter<S: Signature>: Send + Sync + 'static {
 
     /// Parse the output as the signature output type.
     fn parse(&self, output: String) -> Result<S::Output, Error>;
+
+    /// Format the request for the image outputs of the signature from the
+    /// formatted input `messages`: the messages describing the images and the
+    /// number of images to generate. `None` without image outputs.
+    fn format_images(&self, messages: &[Message]) -> Option<(Vec<Message>, usize)> {
+        let _ = messages;
+        None
+    }
+
+    /// Parse the output together with the images generated for the request of
+    /// [`Adapter::format_images`].
+    fn parse_with_images(
+        &self,
+        output: String,
+        images: Vec<MessageContent>,
+    ) -> Result<S::Output, Error> {
+        if !images.is_empty() {
+            return Err(Error::InvalidArgument(
+                "adapter does not support image outputs".into(),
+            ));
+        }
+        self.parse(output)
+    }
 }
@@ -0,0 +1,541 @@
+use std::collections::BTreeSet;
+
+use schemars::Schema;
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Number, Value};
+
+use super::enums;
+
+/// Find the JSON object embedded in `output` that best matches `T`, whose
+/// JSON schema is `schema`.
+///
+/// The scanner is tolerant to the usual LM mistakes: surrounding prose, several
+/// objects in one response, braces inside strings, trailing commas, unquoted or
+/// single-quoted keys and output truncated in the middle of an object. Every
+/// object found (including nested ones) is a candidate and the one that
+/// deserializes as `T` with the fewest repairs wins.
+pub(crate) fn parse<T: DeserializeOwned>(output: &str, schema: &Schema) -> Option<T> {
+    let properties = schema_properties(schema);
+
+    let mut best: Option<(Rank, T)> = None;
+    for candidate in candidates(output) {
+        let mut value = candidate.value.clone();
+        enums::normalize(schema, &mut value);
+        let Ok(value) = serde_json::from_value::<T>(value) else {
+            continue;
+        };
+
+        let rank = Rank::new(&candidate, &properties);
+        if best.as_ref().is_none_or(|(best, _)| rank < *best) {
+            best = Some((rank, value));
+        }
+    }
+
+    best.map(|(_, value)| value)
+}
+
+/// A JSON object recovered from the output.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Candidate {
+    /// The parsed (and possibly repaired) object.
+    pub value: Value,
+    /// Number of repairs applied while parsing the enclosing top-level object.
+    pub repairs: usize,
+    /// Nesting depth of the object in the enclosing top-level object.
+    pub depth: usize,
+    /// Byte offset of the enclosing top-level object in the output.
+    pub offset: usize,
+}
+
+/// Scan `output` for JSON objects and return all of them, including objects
+/// nested inside other objects, in the order they appear.
+pub(crate) fn candidates(output: &str) -> Vec<Candidate> {
+    let mut candidates = vec![];
+
+    let mut pos = 0;
+    while let Some(start) = output[pos..].find('{').map(|i| pos + i) {
+        let mut parser = Parser::new(output, start);
+        match parser.parse_value() {
+            Ok(Some(value)) => {
+                collect(&value, parser.repairs, 0, start, &mut candidates);
+                pos = parser.pos;
+            }
+            _ => pos = start + 1,
+        }
+    }
+
+    candidates
+}
+
+fn collect(value: &Value, repairs: usize, depth: usize, offset: usize, out: &mut Vec<Candidate>) {
+    match value {
+        Value::Object(kv) => {
+            out.push(Candidate {
+                value: value.clone(),
+                repairs,
+                depth,
+                offset,
+            });
+            for v in kv.values() {
+                collect(v, repairs, depth + 1, offset, out);
+            }
+        }
+        Value::Array(items) => {
+            for v in items {
+                collect(v, repairs, depth + 1, offset, out);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Ordering of candidates that deserialize successfully. Lower is better.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct Rank {
+    repairs: usize,
+    depth: usize,
+    missing: usize,
+    unknown: usize,
+    offset: usize,
+}
+
+impl Rank {
+    fn new(candidate: &Candidate, properties: &BTreeSet<String>) -> Self {
+        let keys = candidate
+            .value
+            .as_object()
+            .map(|kv| kv.keys().cloned().collect::<BTreeSet<_>>())
+            .unwrap_or_default();
+
+        Self {
+            repairs: candidate.repairs,
+            depth: candidate.depth,
+            missing: properties.difference(&keys).count(),
+            unknown: keys.difference(properties).count(),
+            offset: candidate.offset,
+        }
+    }
+}
+
+fn schema_properties(schema: &Schema) -> BTreeSet<String> {
+    schema
+        .get("properties")
+        .and_then(|p| p.as_object())
+        .map(|p| p.keys().cloned().collect())
+        .unwrap_or_default()
+}
+
+/// Maximum nesting depth of objects and arrays, as in `serde_json`, to stop
+/// on pathological outputs before the recursion overflows the stack.
+const MAX_DEPTH: usize = 128;
+
+/// Marker for input that is not JSON, even after repairs.
+struct Invalid;
+
+/// Tolerant recursive descent JSON parser.
+///
+/// `Ok(None)` means the input ended before a value started, which lets the
+/// caller drop a dangling key or array element of a truncated output.
+struct Parser<'a> {
+    input: &'a str,
+    pos: usize,
+    repairs: usize,
+    depth: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(input: &'a str, pos: usize) -> Self {
+        Self {
+            input,
+            pos,
+            repairs: 0,
+            depth: 0,
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.input[self.pos..].chars().next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    fn skip_ws(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.bump();
+        }
+    }
+
+    fn parse_value(&mut self) -> Result<Option<Value>, Invalid> {
+        self.skip_ws();
+        let value = match self.peek() {
+            None => return Ok(None),
+            Some('{') => self.nested(Self::parse_object)?,
+            Some('[') => self.nested(Self::parse_array)?,
+            Some('"') => Value::String(self.parse_string('"')?),
+            Some('\'') => {
+                self.repairs += 1;
+                Value::String(self.parse_string('\'')?)
+            }
+            Some(c) if c == '-' || c.is_ascii_digit() => self.parse_number()?,
+            Some(c) if c.is_alphabetic() => self.parse_literal()?,
+            Some(_) => return Err(Invalid),
+        };
+        Ok(Some(value))
+    }
+
+    /// Parse an object or array, one level deeper.
+    fn nested(&mut self, parse: fn(&mut Self) -> Result<Value, Invalid>) -> Result<Value, Invalid> {
+        if self.depth >= MAX_DEPTH {
+            return Err(Invalid);
+        }
+        self.depth += 1;
+        let value = parse(self);
+        self.depth -= 1;
+        value
+    }
+
+    fn parse_object(&mut self) -> Result<Value, Invalid> {
+        self.bump(); // {
+        let mut kv = Map::new();
+        let mut trailing = false;
+        loop {
+            self.skip_ws();
+            match self.peek() {
+                None => {
+                    // Truncated output, close the object
+                    self.repairs += 1;
+                    return Ok(Value::Object(kv));
+                }
+                Some('}') => {
+                    if trailing {
+                        // Trailing comma
+                        self.repairs += 1;
+                    }
+                    self.bump();
+                    return Ok(Value::Object(kv));
+                }
+                Some(',') => {
+                    // Duplicate comma
+                    self.bump();
+                    self.repairs += 1;
+                    continue;
+                }
+                _ => {}
+            }
+
+            trailing = false;
+            let key = self.parse_key()?;
+
+            self.skip_ws();
+            match self.peek() {
+                Some(':') => {
+                    self.bump();
+                }
+                None => {
+                    // Truncated after the key, drop it
+                    self.repairs += 1;
+                    return Ok(Value::Object(kv));
+                }
+                Some(_) => return Err(Invalid),
+            }
+
+            match self.parse_value()? {
+                Some(value) => {
+                    kv.insert(key, value);
+                }
+                None => {
+                    // Truncated before the value, drop the key
+                    self.repairs += 1;
+                    return Ok(Value::Object(kv));
+                }
+            }
+
+            self.skip_ws();
+            match self.peek() {
+                Some(',') => {
+                    self.bump();
+                    trailing = true;
+                }
+                Some('}') | None => {}
+                Some('"') => {
+                    // Missing comma between members
+                    self.repairs += 1;
+                }
+                Some(_) => return Err(Invalid),
+            }
+        }
+    }
+
+    fn parse_key(&mut self) -> Result<String, Invalid> {
+        match self.peek() {
+            Some('"') => self.parse_string('"'),
+            Some('\'') => {
+                self.repairs += 1;
+                self.parse_string('\'')
+            }
+            Some(c) if is_ident(c) => {
+                self.repairs += 1;
+                let start = self.pos;
+                while self.peek().is_some_and(is_ident) {
+                    self.bump();
+                }
+                Ok(self.input[start..self.pos].to_string())
+            }
+            _ => Err(Invalid),
+        }
+    }
+
+    fn parse_array(&mut self) -> Result<Value, Invalid> {
+        self.bump(); // [
+        let mut items = vec![];
+        let mut trailing = false;
+        loop {
+            self.skip_ws();
+            match self.peek() {
+                None => {
+                    // Truncated output, close the array
+                    self.repairs += 1;
+                    return Ok(Value::Array(items));
+                }
+                Some(']') => {
+                    if trailing {
+                        // Trailing comma
+                        self.repairs += 1;
+                    }
+                    self.bump();
+                    return Ok(Value::Array(items));
+                }
+                Some(',') => {
+                    // Duplicate comma
+                    self.bump();
+                    self.repairs += 1;
+                    continue;
+                }
+                _ => {}
+            }
+
+            trailing = false;
+            match self.parse_value()? {
+                Some(value) => items.push(value),
+                None => {
+                    self.repairs += 1;
+                    return Ok(Value::Array(items));
+                }
+            }
+
+            self.skip_ws();
+            match self.peek() {
+                Some(',') => {
+                    self.bump();
+                    trailing = true;
+                }
+                Some(']') | None => {}
+                Some(_) => return Err(Invalid),
+            }
+        }
+    }
+
+    fn parse_string(&mut self, quote: char) -> Result<String, Invalid> {
+        self.bump(); // opening quote
+        let mut s = String::new();
+        loop {
+            match self.bump() {
+                None => {
+                    // Truncated output, close the string
+                    self.repairs += 1;
+                    return Ok(s);
+                }
+                Some(c) if c == quote => return Ok(s),
+                Some('\\') => match self.bump() {
+                    None => {
+                        self.repairs += 1;
+                        return Ok(s);
+                    }
+                    Some('n') => s.push('\n'),
+                    Some('t') => s.push('\t'),
+                    Some('r') => s.push('\r'),
+                    Some('b') => s.push('\u{8}'),
+                    Some('f') => s.push('\u{c}'),
+                    Some('u') => s.push(self.parse_unicode_escape()?),
+                    Some(c) => s.push(c),
+                },
+                Some(c) => {
+                    if c.is_control() {
+                        // Raw control characters are not allowed in JSON strings
+                        self.repairs += 1;
+                    }
+                    s.push(c);
+                }
+            }
+        }
+    }
+
+    fn parse_unicode_escape(&mut self) -> Result<char, Invalid> {
+        let hi = self.parse_hex4()?;
+        if !(0xD800..0xDC00).contains(&hi) {
+            return char::from_u32(hi).ok_or(Invalid);
+        }
+
+        // Surrogate pair
+        if self.input[self.pos..].starts_with("\\u") {
+            self.pos += 2;
+            let lo = self.parse_hex4()?;
+            if (0xDC00..0xE000).contains(&lo) {
+                let c = 0x10000 + ((hi - 0xD800) << 10) + (lo - 0xDC00);
+                return char::from_u32(c).ok_or(Invalid);
+            }
+        }
+        self.repairs += 1;
+        Ok(char::REPLACEMENT_CHARACTER)
+    }
+
+    fn parse_hex4(&mut self) -> Result<u32, Invalid> {
+        let hex = self.input.get(self.pos..self.pos + 4).ok_or(Invalid)?;
+        let value = u32::from_str_radix(hex, 16).map_err(|_| Invalid)?;
+        self.pos += 4;
+        Ok(value)
+    }
+
+    fn parse_number(&mut self) -> Result<Value, Invalid> {
+        let start = self.pos;
+        while self
+            .peek()
+            .is_some_and(|c| c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E'))
+        {
+            self.bump();
+        }
+
+        // Drop incomplete exponent or fraction of a truncated number
+        let mut text = &self.input[start..self.pos];
+        let trimmed = text.trim_end_matches(['.', 'e', 'E', '+', '-']);
+        if trimmed.len() != text.len() {
+            self.repairs += 1;
+            text = trimmed;
+        }
+
+        match serde_json::from_str::<Number>(text) {
+            Ok(n) => Ok(Value::Number(n)),
+            Err(_) => Err(Invalid),
+        }
+    }
+
+    fn parse_literal(&mut self) -> Result<Value, Invalid> {
+        let start = self.pos;
+        while self.peek().is_some_and(char::is_alphabetic) {
+            self.bump();
+        }
+
+        match &self.input[start..self.pos] {
+            "true" => Ok(Value::Bool(true)),
+            "false" => Ok(Value::Bool(false)),
+            "null" => Ok(Value::Null),
+            // Python literals
+            "True" => {
+                self.repairs += 1;
+                Ok(Value::Bool(true))
+            }
+            "False" => {
+                self.repairs += 1;
+                Ok(Value::Bool(false))
+            }
+            "None" => {
+                self.repairs += 1;
+                Ok(Value::Null)
+            }
+            _ => Err(Invalid),
+        }
+    }
+}
+
+fn is_ident(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '$' || c == '-'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+    use schemars::{JsonSchema, schema_for};
+    use serde::Deserialize;
+    use serde_json::json;
+
+    #[derive(Debug, Deserialize, JsonSchema, PartialEq)]
+    struct Output {
+        name: String,
+        value: i32,
+    }
+
+    #[rstest]
+    #[case("{\"a\": 1}", json!({"a": 1}), 0)]
+    #[case("{\"a\": \"} { \\\" \", \"b\": [1, 2]}", json!({"a": "} { \" ", "b": [1, 2]}), 0)]
+    #[case("{\"a\": 1,}", json!({"a": 1}), 1)]
+    #[case("{\"a\": [1, 2,],}", json!({"a": [1, 2]}), 2)]
+    #[case("{a: 1, 'b': 'x'}", json!({"a": 1, "b": "x"}), 3)]
+    #[case("{\"a\": True, \"b\": None}", json!({"a": true, "b": null}), 2)]
+    #[case("{\"a\": \"trunc", json!({"a": "trunc"}), 2)]
+    #[case("{\"a\": [1, 2", json!({"a": [1, 2]}), 2)]
+    #[case("{\"a\": 1, \"b\":", json!({"a": 1}), 1)]
+    #[case("{\"a\": 1.", json!({"a": 1}), 2)]
+    #[case("{\"a\": \"\\ud83d\\ude00\"}", json!({"a": "😀"}), 0)]
+    fn test_parse_candidate(
+        #[case] input: &str,
+        #[case] expected: Value,
+        #[case] expected_repairs: usize,
+    ) {
+        let candidates = candidates(input);
+        assert_eq!(candidates[0].value, expected, "Failed for input: {input:?}");
+        assert_eq!(
+            candidates[0].repairs, expected_repairs,
+            "Failed for input: {input:?}"
+        );
+    }
+
+    #[test]
+    fn test_candidates_depth_limit() {
+        // Too deep to parse, without overflowing the stack
+        let arrays = format!("{{\"a\": {}", "[".repeat(100_000));
+        assert_eq!(candidates(&arrays), vec![]);
+        // Only the innermost objects of a truncated output are within the limit
+        let objects = "{\"a\": ".repeat(10_000);
+        let innermost = objects.len() - MAX_DEPTH * "{\"a\": ".len();
+        assert!(candidates(&objects).iter().all(|c| c.offset >= innermost));
+
+        // Deep but within the limit
+        let nested = format!("{{\"a\": {}1{}}}", "[".repeat(100), "]".repeat(100));
+        let candidates = candidates(&nested);
+        assert_eq!(candidates.len(), 1);
+        assert_eq!(candidates[0].repairs, 0);
+    }
+
+    #[test]
+    fn test_candidates_nested_and_sequential() {
+        let candidates = candidates("x {\"a\": {\"b\": 1}} y {\"c\": 2}");
+        let values = candidates.into_iter().map(|c| c.value).collect::<Vec<_>>();
+        assert_eq!(
+            values,
+            vec![json!({"a": {"b": 1}}), json!({"b": 1}), json!({"c": 2})]
+        );
+    }
+
+    #[rstest]
+    #[case("{\"invalid\": true} {\"name\": \"a\", \"value\": 1}")]
+    #[case("{\"name\": \"a\", \"value\": 1,} {\"name\": \"b\"}")]
+    #[case("{\"result\": {\"name\": \"a\", \"value\": 1}}")]
+    #[case("{\"name\": \"b\", \"value\": 1,} {\"name\": \"a\", \"value\": 1}")]
+    #[case("{\"name\": \"a\", \"value\": 1} {\"name\": \"b\", \"value\": 2, \"extra\": 3}")]
+    fn test_parse_picks_best_candidate(#[case] input: &str) {
+        let parsed = parse::<Output>(input, &schema_for!(Output)).unwrap();
+        assert_eq!(
+            parsed,
+            Output {
+                name: "a".to_string(),
+                value: 1
+            },
+            "Failed for input: {input:?}"
+        );
+    }
+}
@@ -0,0 +1,329 @@
+use std::collections::HashSet;
+
+use schemars::Schema;
+use serde_json::Value;
+
+use super::enums;
+
+/// Maximum nesting depth rendered, to stop on pathological schemas.
+const MAX_DEPTH: usize = 32;
+
+/// Renders JSON schemas as readable type descriptions, e.g.
+/// `list[Album {title: string, year: integer<uint16> | null}]`.
+///
+/// Named object types (`$defs` and titled root objects) are expanded the first
+/// time they are rendered and referred to by name afterwards, so one renderer
+/// should be used for all fields of a prompt.
+#[derive(Debug, Default)]
+pub(crate) struct TypeRenderer {
+    seen: HashSet<String>,
+}
+
+impl TypeRenderer {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Render the type of a root schema, e.g. the schema of a signature field.
+    pub(crate) fn render(&mut self, schema: &Schema) -> String {
+        let root = schema.as_value();
+        let mut buf = String::new();
+        match root.get("title").and_then(Value::as_str) {
+            Some(name) if is_struct(root) => self.render_named(root, name, root, &mut buf, 0),
+            _ => self.render_value(root, root, &mut buf, 0),
+        }
+        buf
+    }
+
+    fn render_named(
+        &mut self,
+        root: &Value,
+        name: &str,
+        def: &Value,
+        buf: &mut String,
+        depth: usize,
+    ) {
+        buf.push_str(name);
+        if self.seen.insert(name.to_string()) {
+            buf.push(' ');
+            self.render_value(root, def, buf, depth + 1);
+        }
+    }
+
+    fn render_value(&mut self, root: &Value, schema: &Value, buf: &mut String, depth: usize) {
+        if depth > MAX_DEPTH {
+            buf.push_str("any");
+            return;
+        }
+
+        let schema = match schema {
+            Value::Bool(true) => return buf.push_str("any"),
+            Value::Bool(false) => return buf.push_str("never"),
+            Value::Object(_) => schema,
+            _ => return buf.push_str("any"),
+        };
+
+        // Types handled by adapters
+        match schema.get("x-dars-type").and_then(Value::as_str) {
+            Some("image") => return buf.push_str("Image"),
+            Some("history") => return buf.push_str("History"),
+            _ => {}
+        }
+
+        if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+            // `#` refers to the root, e.g. in recursive types
+            let name = match reference {
+                "#" => root
+                    .get("title")
+                    .and_then(Value::as_str)
+                    .unwrap_or("object"),
+                reference => reference.rsplit('/').next().unwrap_or(reference),
+            };
+            match reference.strip_prefix('#').and_then(|p| root.pointer(p)) {
+                Some(def) if is_struct(def) => self.render_named(root, name, def, buf, depth),
+                Some(def) => self.render_value(root, def, buf, depth + 1),
+                None => buf.push_str(name),
+            }
+            return;
+        }
+
+        if let Some(choices) = enums::choices(schema) {
+            return render_literal(&choices, buf);
+        }
+        if let Some(value) = schema.get("const") {
+            return render_literal(std::slice::from_ref(value), buf);
+        }
+
+        for keyword in ["anyOf", "oneOf"] {
+            if let Some(Value::Array(branches)) = schema.get(keyword) {
+                return self.render_union(root, branches, buf, depth);
+            }
+        }
+        if let Some(Value::Array(branches)) = schema.get("allOf") {
+            for (i, branch) in branches.iter().enumerate() {
+                if i > 0 {
+                    buf.push_str(" & ");
+                }
+                self.render_value(root, branch, buf, depth + 1);
+            }
+            return;
+        }
+
+        match schema.get("type") {
+            // e.g. `["string", "null"]` for optional values
+            Some(Value::Array(types)) => {
+                let branches = types
+                    .iter()
+                    .map(|ty| {
+                        let mut branch = schema.clone();
+                        branch["type"] = ty.clone();
+                        branch
+                    })
+                    .collect::<Vec<_>>();
+                self.render_union(root, &branches, buf, depth);
+            }
+            Some(Value::String(ty)) => self.render_type(root, ty, schema, buf, depth),
+            _ if schema.get("properties").is_some() => {
+                self.render_type(root, "object", schema, buf, depth)
+            }
+            _ if schema.get("items").is_some() || schema.get("prefixItems").is_some() => {
+                self.render_type(root, "array", schema, buf, depth)
+            }
+            _ => buf.push_str("any"),
+        }
+    }
+
+    fn render_union(&mut self, root: &Value, branches: &[Value], buf: &mut String, depth: usize) {
+        for (i, branch) in branches.iter().enumerate() {
+            if i > 0 {
+                buf.push_str(" | ");
+            }
+            self.render_value(root, branch, buf, depth + 1);
+        }
+    }
+
+    fn render_type(
+        &mut self,
+        root: &Value,
+        ty: &str,
+        schema: &Value,
+        buf: &mut String,
+        depth: usize,
+    ) {
+        match ty {
+            "string" | "integer" | "number" => {
+                buf.push_str(ty);
+                // e.g. `integer<uint8>`, `string<date-time>`
+                if let Some(format) = schema.get("format").and_then(Value::as_str) {
+                    buf.push_str(&format!("<{format}>"));
+                }
+            }
+            "array" => self.render_array(root, schema, buf, depth),
+            "object" => self.render_object(root, schema, buf, depth),
+            // `boolean`, `null` and unknown types
+            ty => buf.push_str(ty),
+        }
+    }
+
+    fn render_array(&mut self, root: &Value, schema: &Value, buf: &mut String, depth: usize) {
+        // Tuples
+        if let Some(Value::Array(prefix)) = schema.get("prefixItems") {
+            buf.push_str("tuple[");
+            for (i, item) in prefix.iter().enumerate() {
+                if i > 0 {
+                    buf.push_str(", ");
+                }
+                self.render_value(root, item, buf, depth + 1);
+            }
+            if schema
+                .get("items")
+                .is_some_and(|items| items != &Value::Bool(false))
+            {
+                buf.push_str(", ...");
+            }
+            buf.push(']');
+            return;
+        }
+
+        let unique = schema.get("uniqueItems") == Some(&Value::Bool(true));
+        buf.push_str(if unique { "set[" } else { "list[" });
+        match schema.get("items") {
+            Some(items) => self.render_value(root, items, buf, depth + 1),
+            None => buf.push_str("any"),
+        }
+        buf.push(']');
+    }
+
+    fn render_object(&mut self, root: &Value, schema: &Value, buf: &mut String, depth: usize) {
+        let properties = schema.get("properties").and_then(Value::as_object);
+        let additional = schema.get("additionalProperties");
+
+        match (properties, additional) {
+            (Some(properties), _) if !properties.is_empty() => {
+                buf.push('{');
+                for (i, (name, value)) in properties.iter().enumerate() {
+                    if i > 0 {
+                        buf.push_str(", ");
+                    }
+                    buf.push_str(name);
+                    buf.push_str(": ");
+                    self.render_value(root, value, buf, depth + 1);
+                }
+                buf.push('}');
+            }
+            (_, Some(Value::Bool(false))) => buf.push_str("{}"),
+            // Maps
+            (_, Some(value)) => {
+                buf.push_str("dict[string, ");
+                self.render_value(root, value, buf, depth + 1);
+                buf.push(']');
+            }
+            (_, None) => buf.push_str("dict[string, any]"),
+        }
+    }
+}
+
+/// Returns whether the schema is an object with named properties.
+fn is_struct(schema: &Value) -> bool {
+    schema
+        .get("properties")
+        .and_then(Value::as_object)
+        .is_some_and(|p| !p.is_empty())
+}
+
+/// Render the allowed values, e.g. `Literal['positive', 'negative']`.
+fn render_literal(choices: &[Value], buf: &mut String) {
+    let choices = choices
+        .iter()
+        .map(|c| match c {
+            Value::String(s) => format!("'{}'", s.replace('\'', "\\'")),
+            c => c.to_string(),
+        })
+        .collect::<Vec<_>>();
+    buf.push_str(&format!("Literal[{}]", choices.join(", ")));
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeMap, BTreeSet};
+
+    use rstest::rstest;
+    use schemars::{JsonSchema, json_schema, schema_for};
+
+    use super::*;
+
+    #[derive(JsonSchema)]
+    #[allow(dead_code)]
+    struct Track {
+        title: String,
+        seconds: u32,
+    }
+
+    #[derive(JsonSchema)]
+    #[allow(dead_code)]
+    struct Album {
+        title: String,
+        year: Option<u16>,
+        tracks: Vec<Track>,
+        bonus: Option<Track>,
+    }
+
+    #[derive(JsonSchema)]
+    #[allow(dead_code)]
+    struct Node {
+        value: i64,
+        children: Vec<Node>,
+    }
+
+    #[derive(JsonSchema)]
+    #[allow(dead_code)]
+    #[serde(rename_all = "lowercase")]
+    enum Label {
+        Positive,
+        Negative,
+    }
+
+    #[rstest]
+    #[case(schema_for!(String), "string")]
+    #[case(schema_for!(bool), "boolean")]
+    #[case(schema_for!(u8), "integer<uint8>")]
+    #[case(schema_for!(f64), "number<double>")]
+    #[case(schema_for!(Option<String>), "string | null")]
+    #[case(schema_for!(Vec<i32>), "list[integer<int32>]")]
+    #[case(schema_for!(BTreeSet<String>), "set[string]")]
+    #[case(schema_for!(BTreeMap<String, f32>), "dict[string, number<float>]")]
+    #[case(schema_for!((String, bool)), "tuple[string, boolean]")]
+    #[case(schema_for!(Label), "Literal['positive', 'negative']")]
+    #[case(schema_for!(Option<Label>), "Literal['positive', 'negative'] | null")]
+    #[case(schema_for!(Vec<Label>), "list[Literal['positive', 'negative']]")]
+    #[case(schema_for!(serde_json::Value), "any")]
+    #[case(json_schema!({"type": "string", "format": "date-time"}), "string<date-time>")]
+    #[case(json_schema!({"const": 42}), "Literal[42]")]
+    #[case(json_schema!({"type": "object"}), "dict[string, any]")]
+    #[case(json_schema!({"anyOf": [{"type": "string"}, {"type": "integer"}]}), "string | integer")]
+    fn test_render(#[case] schema: Schema, #[case] expected: &str) {
+        assert_eq!(TypeRenderer::new().render(&schema), expected);
+    }
+
+    #[test]
+    fn test_render_definitions_once() {
+        let mut types = TypeRenderer::new();
+        assert_eq!(
+            types.render(&schema_for!(Album)),
+            "Album {bonus: Track {seconds: integer<uint32>, title: string} | null, \
+            title: string, tracks: list[Track], year: integer<uint16> | null}"
+        );
+
+        // Later fields refer to the definitions by name
+        assert_eq!(types.render(&schema_for!(Vec<Album>)), "list[Album]");
+        assert_eq!(types.render(&schema_for!(Track)), "Track");
+    }
+
+    #[test]
+    fn test_render_recursive() {
+        assert_eq!(
+            TypeRenderer::new().render(&schema_for!(Node)),
+            "Node {children: list[Node], value: integer<int64>}"
+        );
+    }
+}
@@ -0,0 +1,145 @@
+use std::path::Path;
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use schemars::JsonSchema;
+use serde::ser::SerializeStruct;
+use serde::{Deserialize, Serialize, Serializer};
+
+use crate::lm::MessageContent;
+use crate::{Error, media};
+
+/// Audio used as an input field, e.g. a recording to transcribe.
+///
+/// In adapter inputs the audio is sent as a separate part of the message rather
+/// than inlined in the text.
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+pub struct Audio {
+    #[schemars(description = "Base64 encoded audio data")]
+    pub data: String,
+    #[schemars(description = "Audio format, e.g. `wav` or `mp3`")]
+    pub format: String,
+}
+
+impl Audio {
+    /// Encode the audio data. The format is detected from the content.
+    pub fn from_bytes(bytes: impl AsRef<[u8]>) -> Result<Self, Error> {
+        let bytes = bytes.as_ref();
+        let format = sniff_format(bytes).ok_or_else(|| {
+            Error::InvalidArgument("unsupported or unrecognized audio format".into())
+        })?;
+        Ok(Self::encode(format, bytes))
+    }
+
+    /// Read the audio file. The format is detected from the content, falling
+    /// back to the file extension.
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path)?;
+        let format = sniff_format(&bytes)
+            .or_else(|| format_from_extension(path))
+            .ok_or_else(|| {
+                Error::InvalidArgument(format!(
+                    "unsupported or unrecognized audio format: {}",
+                    path.display()
+                ))
+            })?;
+        Ok(Self::encode(format, &bytes))
+    }
+
+    /// Audio from base64 encoded data, with or without the `data:` url prefix.
+    pub fn from_base64(data: &str) -> Result<Self, Error> {
+        let data = match data.split_once(";base64,") {
+            Some((prefix, data)) if prefix.starts_with("data:") => data,
+            _ => data,
+        };
+        let bytes = STANDARD
+            .decode(data.trim())
+            .map_err(|e| Error::InvalidArgument(format!("invalid base64 audio data: {e}")))?;
+        Self::from_bytes(bytes)
+    }
+
+    /// Returns the MIME type of the audio format.
+    pub fn mime_type(&self) -> String {
+        mime_type(&self.format)
+    }
+
+    fn encode(format: &str, bytes: &[u8]) -> Self {
+        Self {
+            data: STANDARD.encode(bytes),
+            format: format.to_string(),
+        }
+    }
+}
+
+/// Returns the MIME type of an audio format, e.g. `audio/mpeg` for `mp3`.
+pub(crate) fn mime_type(format: &str) -> String {
+    match format {
+        "mp3" => "audio/mpeg".to_string(),
+        "m4a" => "audio/mp4".to_string(),
+        format => format!("audio/{format}"),
+    }
+}
+
+/// Detect the audio format from its magic bytes.
+fn sniff_format(bytes: &[u8]) -> Option<&'static str> {
+    if bytes.starts_with(b"RIFF") && bytes.get(8..12) == Some(b"WAVE") {
+        Some("wav")
+    } else if bytes.starts_with(b"ID3") || is_mp3_frame(bytes) {
+        Some("mp3")
+    } else if bytes.starts_with(b"fLaC") {
+        Some("flac")
+    } else if bytes.starts_with(b"OggS") {
+        Some("ogg")
+    } else if bytes.get(4..11) == Some(b"ftypM4A") {
+        Some("m4a")
+    } else if bytes.starts_with(&[0x1A, 0x45, 0xDF, 0xA3]) {
+        Some("webm")
+    } else {
+        None
+    }
+}
+
+/// Returns whether the bytes start with an MPEG audio layer III frame header:
+/// 11 sync bits, a version other than the reserved `01` and layer bits `01`.
+/// AAC ADTS headers share the sync bits but have layer bits `00`.
+fn is_mp3_frame(bytes: &[u8]) -> bool {
+    matches!(bytes, [0xFF, b, ..] if b & 0xE0 == 0xE0 && b & 0x18 != 0x08 && b & 0x06 == 0x02)
+}
+
+fn format_from_extension(path: &Path) -> Option<&'static str> {
+    let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+    let format = match ext.as_str() {
+        "wav" => "wav",
+        "mp3" => "mp3",
+        "flac" => "flac",
+        "ogg" | "oga" => "ogg",
+        "m4a" => "m4a",
+        "webm" => "webm",
+        _ => return None,
+    };
+    Some(format)
+}
+
+/// Serializes as `{"data": ..., "format": ...}`. In adapter inputs the audio
+/// becomes a separate part of the message.
+impl Serialize for Audio {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        media::serialize_media(
+            serializer,
+            || MessageContent::Audio {
+                data: self.data.clone(),
+                format: self.format.clone(),
+            },
+            |serializer| {
+                let mut audio = serializer.serialize_struct("Audio", 2)?;
+                audio.serialize_field("data", &self.data)?;
+                audio.serialize_field("format", &self.format)?;
+                audio.end()
+            },
+        )
+    }
+}
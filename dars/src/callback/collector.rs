@@ -0,0 +1,180 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use schemars::Schema;
+use serde::Serialize;
+use serde_json::Value;
+
+use super::{CallInfo, Callback};
+use crate::Error;
+use crate::lm::Message;
+
+/// A node of the call tree built by [`TraceCollector`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Trace {
+    pub id: u64,
+    pub event: TraceEvent,
+    /// Time between the start and end events, `None` for unfinished calls and
+    /// adapter events.
+    pub duration: Option<Duration>,
+    /// Calls made by this call in the order they started.
+    pub children: Vec<Trace>,
+}
+
+/// What happened in a [`Trace`] node. Errors are kept as their message.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TraceEvent {
+    Module {
+        name: String,
+        input: Value,
+        output: Option<Result<Value, String>>,
+    },
+    Lm {
+        messages: Vec<Message>,
+        schema: Option<Schema>,
+        completion: Option<Result<String, String>>,
+    },
+    AdapterFormat {
+        messages: Vec<Message>,
+        schema: Option<Schema>,
+    },
+    AdapterParse {
+        completion: String,
+        output: Result<Value, String>,
+    },
+}
+
+/// Callback collecting events into one [`Trace`] tree per top-level call.
+#[derive(Default)]
+pub struct TraceCollector {
+    state: Mutex<State>,
+}
+
+#[derive(Default)]
+struct State {
+    nodes: HashMap<u64, Node>,
+    roots: Vec<u64>,
+}
+
+struct Node {
+    event: TraceEvent,
+    started: Instant,
+    duration: Option<Duration>,
+    children: Vec<u64>,
+}
+
+impl TraceCollector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the traces of the top-level calls in the order they started.
+    pub fn traces(&self) -> Vec<Trace> {
+        let state = self.state.lock().unwrap();
+        state.roots.iter().map(|id| state.build(*id)).collect()
+    }
+
+    /// Drop the collected traces.
+    pub fn clear(&self) {
+        *self.state.lock().unwrap() = State::default();
+    }
+
+    fn start(&self, call: &CallInfo, event: TraceEvent) {
+        let mut state = self.state.lock().unwrap();
+        match call.parent.and_then(|p| state.nodes.get_mut(&p)) {
+            Some(parent) => parent.children.push(call.id),
+            None => state.roots.push(call.id),
+        }
+        state.nodes.insert(
+            call.id,
+            Node {
+                event,
+                started: Instant::now(),
+                duration: None,
+                children: vec![],
+            },
+        );
+    }
+
+    fn end(&self, call: &CallInfo, f: impl FnOnce(&mut TraceEvent)) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(node) = state.nodes.get_mut(&call.id) {
+            node.duration = Some(node.started.elapsed());
+            f(&mut node.event);
+        }
+    }
+}
+
+impl State {
+    fn build(&self, id: u64) -> Trace {
+        let node = &self.nodes[&id];
+        Trace {
+            id,
+            event: node.event.clone(),
+            duration: node.duration,
+            children: node.children.iter().map(|c| self.build(*c)).collect(),
+        }
+    }
+}
+
+impl Callback for TraceCollector {
+    fn on_module_start(&self, call: &CallInfo, name: &str, input: &Value) {
+        self.start(
+            call,
+            TraceEvent::Module {
+                name: name.to_string(),
+                input: input.clone(),
+                output: None,
+            },
+        );
+    }
+
+    fn on_module_end(&self, call: &CallInfo, result: Result<&Value, &Error>) {
+        self.end(call, |event| {
+            if let TraceEvent::Module { output, .. } = event {
+                *output = Some(result.cloned().map_err(|e| e.to_string()));
+            }
+        });
+    }
+
+    fn on_lm_start(&self, call: &CallInfo, messages: &[Message], schema: Option<&Schema>) {
+        self.start(
+            call,
+            TraceEvent::Lm {
+                messages: messages.to_vec(),
+                schema: schema.cloned(),
+                completion: None,
+            },
+        );
+    }
+
+    fn on_lm_end(&self, call: &CallInfo, result: Result<&str, &Error>) {
+        self.end(call, |event| {
+            if let TraceEvent::Lm { completion, .. } = event {
+                *completion = Some(result.map(str::to_string).map_err(|e| e.to_string()));
+            }
+        });
+    }
+
+    fn on_adapter_format(&self, call: &CallInfo, messages: &[Message], schema: Option<&Schema>) {
+        self.start(
+            call,
+            TraceEvent::AdapterFormat {
+                messages: messages.to_vec(),
+                schema: schema.cloned(),
+            },
+        );
+    }
+
+    fn on_adapter_parse(&self, call: &CallInfo, completion: &str, output: Result<&Value, &Error>) {
+        self.start(
+            call,
+            TraceEvent::AdapterParse {
+                completion: completion.to_string(),
+                output: output.cloned().map_err(|e| e.to_string()),
+            },
+        );
+    }
+}
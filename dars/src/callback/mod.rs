@@ -0,0 +1,268 @@
+//! Hooks into module, LM and adapter calls.
+//!
+//! Callbacks are attached to a call with [`CallContext::with_callback`] and see
+//! the events of everything that call runs:
+//!
+//! ```ignore
+//! let collector = Arc::new(TraceCollector::new());
+//! let output = CallContext::current()
+//!     .with_callback(collector.clone())
+//!     .scope(module.call(input))
+//!     .await?;
+//! println!("{:#?}", collector.traces());
+//! ```
+//!
+//! Every event carries a [`CallInfo`] with a unique id and the id of the
+//! enclosing module call, so callbacks can rebuild the call tree.
+
+use std::fmt::Debug;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use schemars::Schema;
+use serde::Serialize;
+use serde_json::Value;
+use tracing::Instrument;
+
+use crate::lm::{self, LM, Message, MessageContent};
+use crate::{CallContext, Error, otel};
+
+mod collector;
+pub use collector::{Trace, TraceCollector, TraceEvent};
+
+/// Receives the events of the calls it's attached to. All methods default to
+/// doing nothing.
+#[allow(unused_variables)]
+pub trait Callback: Send + Sync + 'static {
+    /// A module was called with `input`.
+    fn on_module_start(&self, call: &CallInfo, name: &str, input: &Value) {}
+
+    /// A module call finished. `call` is the same as in the start event.
+    fn on_module_end(&self, call: &CallInfo, output: Result<&Value, &Error>) {}
+
+    /// An LM was called with the messages and output schema.
+    fn on_lm_start(&self, call: &CallInfo, messages: &[Message], schema: Option<&Schema>) {}
+
+    /// An LM call finished with the raw completion.
+    fn on_lm_end(&self, call: &CallInfo, completion: Result<&str, &Error>) {}
+
+    /// An adapter formatted the module input as messages.
+    fn on_adapter_format(&self, call: &CallInfo, messages: &[Message], schema: Option<&Schema>) {}
+
+    /// An adapter parsed a completion.
+    fn on_adapter_parse(&self, call: &CallInfo, completion: &str, output: Result<&Value, &Error>) {}
+}
+
+/// Identifies the call an event belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CallInfo {
+    /// Unique id of the call.
+    pub id: u64,
+    /// Id of the enclosing module call, `None` for top-level calls.
+    pub parent: Option<u64>,
+}
+
+impl CallInfo {
+    fn new(parent: Option<u64>) -> Self {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+        Self {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            parent,
+        }
+    }
+}
+
+/// Callbacks attached to a [`CallContext`].
+#[derive(Clone, Default)]
+pub struct Callbacks(Vec<Arc<dyn Callback>>);
+
+impl Callbacks {
+    pub fn push(&mut self, callback: Arc<dyn Callback>) {
+        self.0.push(callback);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    fn emit(&self, f: impl Fn(&dyn Callback)) {
+        for callback in &self.0 {
+            f(callback.as_ref());
+        }
+    }
+}
+
+impl Debug for Callbacks {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Callbacks({})", self.0.len())
+    }
+}
+
+impl PartialEq for Callbacks {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.len() == other.0.len() && self.0.iter().zip(&other.0).all(|(a, b)| Arc::ptr_eq(a, b))
+    }
+}
+
+/// Run a module call, emitting the module events around it (and a span with
+/// the `otel` feature). Module implementations can use it to show up in traces:
+///
+/// ```ignore
+/// async fn call(&self, input: Self::Input) -> Result<Self::Output, Error> {
+///     traced("Rag", input, |input| async move { ... }).await
+/// }
+/// ```
+pub async fn traced<I, O, F>(name: &str, input: I, f: impl FnOnce(I) -> F) -> Result<O, Error>
+where
+    I: Serialize,
+    O: Serialize,
+    F: Future<Output = Result<O, Error>>,
+{
+    let span = otel::module_span(name);
+    let result = module_events(name, input, f).instrument(span.clone()).await;
+    otel::record_module_result(&span, &result);
+    result
+}
+
+async fn module_events<I, O, F>(name: &str, input: I, f: impl FnOnce(I) -> F) -> Result<O, Error>
+where
+    I: Serialize,
+    O: Serialize,
+    F: Future<Output = Result<O, Error>>,
+{
+    let ctx = CallContext::current();
+    if ctx.callbacks.is_empty() {
+        return f(input).await;
+    }
+
+    let call = CallInfo::new(ctx.call_id);
+    let input_value = to_value(&input);
+    ctx.callbacks
+        .emit(|cb| cb.on_module_start(&call, name, &input_value));
+
+    let result = ctx.clone().with_call_id(call.id).scope(f(input)).await;
+    let output = result.as_ref().map(to_value);
+    ctx.callbacks
+        .emit(|cb| cb.on_module_end(&call, output.as_ref().map_err(|e| *e)));
+    result
+}
+
+/// Call `lm`, emitting the LM events around it (and a span with the `otel` feature)
+/// and recording it in the global history.
+pub(crate) async fn lm_call(
+    lm: &dyn LM,
+    messages: Vec<Message>,
+    schema: Option<Schema>,
+) -> Result<String, Error> {
+    lm_request(
+        messages,
+        schema,
+        |messages, schema| lm.call(messages, schema),
+        String::clone,
+    )
+    .await
+}
+
+/// Like [`lm_call`], but generating `n` images with [`LM::generate_images`].
+/// The images are rendered as text (without their data) for the events and
+/// the history.
+pub(crate) async fn lm_images(
+    lm: &dyn LM,
+    messages: Vec<Message>,
+    n: usize,
+) -> Result<Vec<MessageContent>, Error> {
+    lm_request(
+        messages,
+        None,
+        |messages, _| lm.generate_images(messages, n),
+        |images: &Vec<MessageContent>| {
+            images
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join("\n")
+        },
+    )
+    .await
+}
+
+/// Run an LM request, with `completion` rendering its response as text.
+async fn lm_request<T, F>(
+    messages: Vec<Message>,
+    schema: Option<Schema>,
+    request: impl FnOnce(Vec<Message>, Option<Schema>) -> F,
+    completion: impl Fn(&T) -> String,
+) -> Result<T, Error>
+where
+    F: Future<Output = Result<T, Error>>,
+{
+    let history = lm::history();
+    let recorded = history.is_enabled().then(|| messages.clone());
+
+    let span = otel::lm_span(&messages);
+    let result = lm_events(messages, schema, request, &completion)
+        .instrument(span.clone())
+        .await;
+    let text = result.as_ref().map(&completion);
+    otel::record_lm_result(&span, text.as_deref().map_err(|e| *e));
+
+    if let (Some(messages), Ok(text)) = (recorded, text) {
+        history.record(messages, text);
+    }
+    result
+}
+
+async fn lm_events<T, F>(
+    messages: Vec<Message>,
+    schema: Option<Schema>,
+    request: impl FnOnce(Vec<Message>, Option<Schema>) -> F,
+    completion: impl Fn(&T) -> String,
+) -> Result<T, Error>
+where
+    F: Future<Output = Result<T, Error>>,
+{
+    let ctx = CallContext::current();
+    if ctx.callbacks.is_empty() {
+        return request(messages, schema).await;
+    }
+
+    let call = CallInfo::new(ctx.call_id);
+    ctx.callbacks
+        .emit(|cb| cb.on_lm_start(&call, &messages, schema.as_ref()));
+    let result = request(messages, schema).await;
+    let text = result.as_ref().map(completion);
+    ctx.callbacks
+        .emit(|cb| cb.on_lm_end(&call, text.as_deref().map_err(|e| *e)));
+    result
+}
+
+/// Emit the adapter format event.
+pub(crate) fn adapter_format(messages: &[Message], schema: Option<&Schema>) {
+    let ctx = CallContext::current();
+    if ctx.callbacks.is_empty() {
+        return;
+    }
+
+    let call = CallInfo::new(ctx.call_id);
+    ctx.callbacks
+        .emit(|cb| cb.on_adapter_format(&call, messages, schema));
+}
+
+/// Emit the adapter parse event.
+pub(crate) fn adapter_parse<O: Serialize>(completion: &str, output: &Result<O, Error>) {
+    let ctx = CallContext::current();
+    if ctx.callbacks.is_empty() {
+        return;
+    }
+
+    let call = CallInfo::new(ctx.call_id);
+    let output = output.as_ref().map(to_value);
+    ctx.callbacks
+        .emit(|cb| cb.on_adapter_parse(&call, completion, output.as_ref().map_err(|e| *e)));
+}
+
+/// Values passed to callbacks. A failure to serialize must not fail the call,
+/// so it's reported as `null`.
+fn to_value<T: Serialize>(value: &T) -> Value {
+    serde_json::to_value(value).unwrap_or_default()
+}
@@ -0,0 +1,124 @@
+use std::cell::RefCell;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use crate::callback::{Callback, Callbacks};
+
+thread_local! {
+    static CURRENT: RefCell<CallContext> = RefCell::new(CallContext::default());
+}
+
+/// Per-call options that flow from a module down to the modules and LMs it calls.
+///
+/// The context is attached to a future with [`CallContext::scope`] and is visible
+/// through [`CallContext::current`] in everything that future awaits, so nested
+/// modules don't need to thread it through their inputs.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CallContext {
+    /// Overrides the sampling temperature of the LM.
+    pub temperature: Option<f32>,
+    /// Identifies the attempt in modules that sample several outputs for the
+    /// same input. LMs that cache responses should include it in the cache key.
+    pub rollout_id: Option<usize>,
+    /// Feedback on previous attempts that `Predict` adds to the prompt.
+    pub feedback: Option<String>,
+    /// Callbacks receiving the events of the call.
+    pub callbacks: Callbacks,
+    /// Id of the enclosing module call, set while callbacks are attached.
+    pub call_id: Option<u64>,
+}
+
+impl CallContext {
+    /// Returns the context of the current call.
+    pub fn current() -> CallContext {
+        CURRENT.with(|c| c.borrow().clone())
+    }
+
+    pub fn with_temperature(self, temperature: f32) -> Self {
+        Self {
+            temperature: Some(temperature),
+            ..self
+        }
+    }
+
+    pub fn with_rollout_id(self, rollout_id: usize) -> Self {
+        Self {
+            rollout_id: Some(rollout_id),
+            ..self
+        }
+    }
+
+    pub fn with_feedback(self, feedback: impl Into<String>) -> Self {
+        Self {
+            feedback: Some(feedback.into()),
+            ..self
+        }
+    }
+
+    /// Attach `callback` in addition to the already attached ones.
+    pub fn with_callback(mut self, callback: Arc<dyn Callback>) -> Self {
+        self.callbacks.push(callback);
+        self
+    }
+
+    pub fn with_call_id(self, call_id: u64) -> Self {
+        Self {
+            call_id: Some(call_id),
+            ..self
+        }
+    }
+
+    /// Run `fut` with this context as the current one.
+    pub fn scope<F: Future>(self, fut: F) -> Scoped<F> {
+        Scoped {
+            ctx: Some(self),
+            inner: Box::pin(fut),
+        }
+    }
+}
+
+/// Future returned by [`CallContext::scope`].
+pub struct Scoped<F> {
+    ctx: Option<CallContext>,
+    inner: Pin<Box<F>>,
+}
+
+impl<F: Future> Future for Scoped<F> {
+    type Output = F::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = &mut *self;
+        let ctx = this
+            .ctx
+            .take()
+            .expect("context is restored after each poll");
+        let _guard = Guard::enter(ctx, &mut this.ctx);
+        this.inner.as_mut().poll(cx)
+    }
+}
+
+/// Swaps the scoped context in for the duration of a poll, restoring the
+/// outer one on drop (also on panic).
+struct Guard<'a> {
+    outer: Option<CallContext>,
+    slot: &'a mut Option<CallContext>,
+}
+
+impl<'a> Guard<'a> {
+    fn enter(ctx: CallContext, slot: &'a mut Option<CallContext>) -> Self {
+        let outer = CURRENT.with(|c| c.replace(ctx));
+        Self {
+            outer: Some(outer),
+            slot,
+        }
+    }
+}
+
+impl Drop for Guard<'_> {
+    fn drop(&mut self) {
+        let outer = self.outer.take().unwrap_or_default();
+        *self.slot = Some(CURRENT.with(|c| c.replace(outer)));
+    }
+}
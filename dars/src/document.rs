@@ -0,0 +1,313 @@
+use std::ops::{Bound, RangeBounds};
+use std::path::Path;
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use schemars::JsonSchema;
+use serde::ser::SerializeStruct;
+use serde::{Deserialize, Serialize, Serializer};
+
+use crate::lm::{Message, MessageContent};
+use crate::{Error, media};
+
+const PDF_MIME_TYPE: &str = "application/pdf";
+
+/// A PDF or text document used as an input field.
+///
+/// PDFs are passed to the LM as a file part, for backends that accept files.
+/// Backends rejecting file parts with [`Error::UnsupportedContent`] get the
+/// extracted text instead. Text documents, and PDFs converted with
+/// [`Document::into_text`], are passed as the text of their pages.
+///
+/// ```ignore
+/// let doc = Document::from_path("report.pdf")?.with_pages(1..=3)?;
+/// ```
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+pub struct Document {
+    pub filename: Option<String>,
+    pub content: DocumentContent,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum DocumentContent {
+    /// Base64 encoded file.
+    File { mime_type: String, data: String },
+    /// Text of the pages.
+    Pages { pages: Vec<Page> },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct Page {
+    /// Page number, starting at 1.
+    pub number: usize,
+    pub text: String,
+}
+
+impl Document {
+    /// Text document. Form feeds (`\x0C`) separate the pages.
+    pub fn from_text(text: impl AsRef<str>) -> Self {
+        let pages = text
+            .as_ref()
+            .split('\x0C')
+            .enumerate()
+            .map(|(i, text)| Page {
+                number: i + 1,
+                text: text.to_string(),
+            })
+            .collect();
+        Self {
+            filename: None,
+            content: DocumentContent::Pages { pages },
+        }
+    }
+
+    /// PDF or UTF-8 text document.
+    pub fn from_bytes(bytes: impl AsRef<[u8]>) -> Result<Self, Error> {
+        let bytes = bytes.as_ref();
+        if bytes.starts_with(b"%PDF-") {
+            return Ok(Self {
+                filename: None,
+                content: DocumentContent::File {
+                    mime_type: PDF_MIME_TYPE.to_string(),
+                    data: STANDARD.encode(bytes),
+                },
+            });
+        }
+
+        match std::str::from_utf8(bytes) {
+            Ok(text) => Ok(Self::from_text(text)),
+            Err(_) => Err(Error::InvalidArgument(
+                "unsupported document format, expected PDF or UTF-8 text".into(),
+            )),
+        }
+    }
+
+    /// Read a PDF or UTF-8 text file.
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let mut document = Self::from_bytes(std::fs::read(path)?)?;
+        document.filename = path.file_name().map(|f| f.to_string_lossy().into_owned());
+        Ok(document)
+    }
+
+    pub fn with_filename(mut self, filename: impl Into<String>) -> Self {
+        self.filename = Some(filename.into());
+        self
+    }
+
+    /// Returns the number of pages.
+    pub fn page_count(&self) -> Result<usize, Error> {
+        match &self.content {
+            DocumentContent::Pages { pages } => Ok(pages.len()),
+            DocumentContent::File { data, .. } => pdf::page_count(data),
+        }
+    }
+
+    /// Keep only the pages in `range` (1-based). Text pages keep their numbers,
+    /// PDFs are cut down to the selected pages.
+    pub fn with_pages(self, range: impl RangeBounds<usize>) -> Result<Self, Error> {
+        let in_range = |number: usize| {
+            let after_start = match range.start_bound() {
+                Bound::Included(start) => number >= *start,
+                Bound::Excluded(start) => number > *start,
+                Bound::Unbounded => true,
+            };
+            let before_end = match range.end_bound() {
+                Bound::Included(end) => number <= *end,
+                Bound::Excluded(end) => number < *end,
+                Bound::Unbounded => true,
+            };
+            after_start && before_end
+        };
+
+        let content = match self.content {
+            DocumentContent::Pages { pages } => DocumentContent::Pages {
+                pages: pages.into_iter().filter(|p| in_range(p.number)).collect(),
+            },
+            DocumentContent::File { mime_type, data } => DocumentContent::File {
+                data: pdf::select_pages(&data, in_range)?,
+                mime_type,
+            },
+        };
+        Ok(Self { content, ..self })
+    }
+
+    /// Extract the text of the pages, for backends that don't accept files.
+    pub fn into_text(self) -> Result<Self, Error> {
+        let content = match self.content {
+            DocumentContent::File { data, .. } => DocumentContent::Pages {
+                pages: pdf::extract_pages(&data)?,
+            },
+            content => content,
+        };
+        Ok(Self { content, ..self })
+    }
+
+    /// Returns the text of the pages, each headed by its number.
+    fn render_pages(pages: &[Page]) -> String {
+        pages
+            .iter()
+            .map(|p| format!("--- Page {} ---\n{}", p.number, p.text.trim_end()))
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    }
+}
+
+/// Serializes as `{"filename": ..., "content": ...}`. In adapter inputs the
+/// document becomes a file part or the text of its pages.
+impl Serialize for Document {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        media::serialize_media(
+            serializer,
+            || match &self.content {
+                DocumentContent::File { mime_type, data } => MessageContent::File {
+                    data: data.clone(),
+                    mime_type: mime_type.clone(),
+                    filename: self.filename.clone(),
+                },
+                DocumentContent::Pages { pages } => MessageContent::Text {
+                    text: Self::render_pages(pages),
+                },
+            },
+            |serializer| {
+                let mut document = serializer.serialize_struct("Document", 2)?;
+                document.serialize_field("filename", &self.filename)?;
+                document.serialize_field("content", &self.content)?;
+                document.end()
+            },
+        )
+    }
+}
+
+/// Returns whether the messages have file parts.
+pub(crate) fn has_files(messages: &[Message]) -> bool {
+    messages.iter().any(|m| match m {
+        Message::User { content } => content.iter().any(is_file),
+        Message::Assistant { content } => is_file(content),
+        Message::System { .. } => false,
+    })
+}
+
+fn is_file(content: &MessageContent) -> bool {
+    matches!(content, MessageContent::File { .. })
+}
+
+/// Replace the file parts of the messages by the text of their pages, for LMs
+/// that don't accept files.
+pub(crate) fn files_to_text(messages: &mut [Message]) -> Result<(), Error> {
+    for message in messages {
+        match message {
+            Message::User { content } => content.iter_mut().try_for_each(file_to_text)?,
+            Message::Assistant { content } => file_to_text(content)?,
+            Message::System { .. } => {}
+        }
+    }
+    Ok(())
+}
+
+fn file_to_text(content: &mut MessageContent) -> Result<(), Error> {
+    let MessageContent::File {
+        data,
+        mime_type,
+        filename,
+    } = content
+    else {
+        return Ok(());
+    };
+    let document = Document {
+        filename: filename.take(),
+        content: DocumentContent::File {
+            mime_type: std::mem::take(mime_type),
+            data: std::mem::take(data),
+        },
+    };
+    let DocumentContent::Pages { pages } = document.into_text()?.content else {
+        unreachable!("documents converted to text have pages");
+    };
+    *content = MessageContent::Text {
+        text: Document::render_pages(&pages),
+    };
+    Ok(())
+}
+
+#[cfg(feature = "pdf")]
+mod pdf {
+    use base64::Engine;
+    use base64::engine::general_purpose::STANDARD;
+
+    use super::Page;
+    use crate::Error;
+
+    pub(super) fn page_count(data: &str) -> Result<usize, Error> {
+        Ok(load(data)?.get_pages().len())
+    }
+
+    fn load(data: &str) -> Result<lopdf::Document, Error> {
+        let bytes = STANDARD
+            .decode(data)
+            .map_err(|e| Error::InvalidArgument(format!("invalid base64 document data: {e}")))?;
+        lopdf::Document::load_mem(&bytes)
+            .map_err(|e| Error::InvalidArgument(format!("invalid PDF: {e}")))
+    }
+
+    pub(super) fn select_pages(data: &str, keep: impl Fn(usize) -> bool) -> Result<String, Error> {
+        let mut doc = load(data)?;
+        let delete = doc
+            .get_pages()
+            .into_keys()
+            .filter(|n| !keep(*n as usize))
+            .collect::<Vec<_>>();
+        doc.delete_pages(&delete);
+        doc.prune_objects();
+
+        let mut buf = vec![];
+        doc.save_to(&mut buf)
+            .map_err(|e| Error::InvalidArgument(format!("failed to write PDF: {e}")))?;
+        Ok(STANDARD.encode(buf))
+    }
+
+    pub(super) fn extract_pages(data: &str) -> Result<Vec<Page>, Error> {
+        let doc = load(data)?;
+        doc.get_pages()
+            .into_keys()
+            .map(|number| {
+                let text = doc.extract_text(&[number]).map_err(|e| {
+                    Error::InvalidArgument(format!("failed to extract page {number}: {e}"))
+                })?;
+                Ok(Page {
+                    number: number as usize,
+                    text,
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(not(feature = "pdf"))]
+mod pdf {
+    use super::Page;
+    use crate::Error;
+
+    fn unsupported() -> Error {
+        Error::InvalidArgument("reading PDF pages requires the `pdf` feature".into())
+    }
+
+    pub(super) fn page_count(_data: &str) -> Result<usize, Error> {
+        Err(unsupported())
+    }
+
+    pub(super) fn select_pages(
+        _data: &str,
+        _keep: impl Fn(usize) -> bool,
+    ) -> Result<String, Error> {
+        Err(unsupported())
+    }
+
+    pub(super) fn extract_pages(_data: &str) -> Result<Vec<Page>, Error> {
+        Err(unsupported())
+    }
+}
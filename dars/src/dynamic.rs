@@ -0,0 +1,267 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::{Mutex, OnceLock};
+
+use schemars::{Schema, json_schema, schema_for};
+use serde_json::{Map, Value};
+
+use crate::{Error, Field, Image, Signature};
+
+/// Signature defined at runtime, e.g. by configuration, with JSON object inputs
+/// and outputs.
+///
+/// Signatures are parsed from `"inputs -> outputs"`, where fields are separated
+/// by commas and optionally typed: `"question, context -> answer: str, score: float"`.
+/// Untyped fields are strings. The types are `str`, `int`, `float`, `bool`,
+/// `Any`, `Image`, `list[T]`, `dict[str, T]`, `Optional[T]` and
+/// `Literal['a', 'b']`. `Optional` outputs may be omitted. `Image` inputs take
+/// a url or `{"url": ..}` and are sent to the LM as image parts.
+///
+/// Parsed field names are interned to get the `'static` lifetime of [`Field`],
+/// so memory only grows with the number of distinct names.
+#[derive(Debug, Clone)]
+pub struct DynamicSignature {
+    instruction: String,
+    inputs: Vec<Field>,
+    outputs: Vec<Field>,
+    fields: HashMap<String, Schema>,
+    output_schema: Schema,
+}
+
+impl DynamicSignature {
+    /// Create a signature from the input and output fields with their schemas.
+    /// Field names must be unique across the inputs and outputs.
+    pub fn new(
+        inputs: impl IntoIterator<Item = (Field, Schema)>,
+        outputs: impl IntoIterator<Item = (Field, Schema)>,
+    ) -> Result<Self, Error> {
+        let mut fields = HashMap::new();
+        let mut add_field = |field: &Field, schema: Schema| {
+            if fields.insert(field.name.to_string(), schema).is_some() {
+                return Err(Error::InvalidArgument(format!(
+                    "duplicate field `{}`",
+                    field.name
+                )));
+            }
+            Ok(())
+        };
+
+        let inputs = inputs
+            .into_iter()
+            .map(|(field, schema)| {
+                add_field(&field, schema)?;
+                Ok(field)
+            })
+            .collect::<Result<_, Error>>()?;
+
+        let mut properties = Map::new();
+        let mut required = vec![];
+        let outputs = outputs
+            .into_iter()
+            .map(|(field, schema)| {
+                if !is_optional(&schema) {
+                    required.push(Value::from(field.name));
+                }
+                let mut property = schema.clone();
+                if let Some(description) = field.description {
+                    property.insert("description".to_string(), description.into());
+                }
+                properties.insert(field.name.to_string(), property.to_value());
+                add_field(&field, schema)?;
+                Ok(field)
+            })
+            .collect::<Result<_, Error>>()?;
+
+        Ok(Self {
+            instruction: String::new(),
+            inputs,
+            outputs,
+            fields,
+            output_schema: json_schema!({
+                "type": "object",
+                "properties": properties,
+                "required": required,
+            }),
+        })
+    }
+
+    /// Parse a signature from `"inputs -> outputs"`, see [`DynamicSignature`].
+    pub fn parse(signature: &str) -> Result<Self, Error> {
+        let invalid = |reason: String| {
+            Error::InvalidArgument(format!("invalid signature {signature:?}: {reason}"))
+        };
+        let Some((inputs, outputs)) = signature.split_once("->") else {
+            return Err(invalid("expected `inputs -> outputs`".to_string()));
+        };
+
+        let mut names = vec![];
+        let mut parse_fields = |fields: &str| {
+            let mut parsed = vec![];
+            for f in split_top_level(fields).iter().filter(|f| !f.is_empty()) {
+                let (name, ty) = match f.split_once(':') {
+                    Some((name, ty)) => (name.trim(), ty.trim()),
+                    None => (f.as_str(), "str"),
+                };
+                if name.is_empty() || !name.chars().all(|c| c.is_alphanumeric() || c == '_') {
+                    return Err(invalid(format!("invalid field name {name:?}")));
+                }
+                if names.iter().any(|n| n == name) {
+                    return Err(invalid(format!("duplicate field `{name}`")));
+                }
+                names.push(name.to_string());
+
+                let schema = parse_type(ty).map_err(invalid)?;
+                let field = Field {
+                    name: intern(name),
+                    description: None,
+                };
+                parsed.push((field, schema));
+            }
+            Ok(parsed)
+        };
+        let inputs = parse_fields(inputs)?;
+        let outputs = parse_fields(outputs)?;
+        if outputs.is_empty() {
+            return Err(invalid("expected at least one output".to_string()));
+        }
+
+        Self::new(inputs, outputs)
+    }
+
+    pub fn with_instruction(mut self, instruction: impl Into<String>) -> Self {
+        self.instruction = instruction.into();
+        self
+    }
+}
+
+impl Signature for DynamicSignature {
+    type Input = Map<String, Value>;
+    type Output = Map<String, Value>;
+
+    fn instruction(&self) -> &str {
+        &self.instruction
+    }
+
+    fn input_fields(&self) -> &[Field] {
+        &self.inputs
+    }
+
+    fn output_fields(&self) -> &[Field] {
+        &self.outputs
+    }
+
+    fn field(&self, name: &str) -> Option<&Schema> {
+        self.fields.get(name)
+    }
+
+    fn output_schema(&self) -> Schema {
+        self.output_schema.clone()
+    }
+}
+
+/// Returns the interned `name`, leaking each distinct name once.
+fn intern(name: &str) -> &'static str {
+    static NAMES: OnceLock<Mutex<HashSet<&'static str>>> = OnceLock::new();
+    let mut names = NAMES.get_or_init(Default::default).lock().unwrap();
+    match names.get(name) {
+        Some(name) => name,
+        None => {
+            let name = String::leak(name.to_string());
+            names.insert(name);
+            name
+        }
+    }
+}
+
+/// Parse a type of the signature string into its JSON schema.
+fn parse_type(ty: &str) -> Result<Schema, String> {
+    let ty = ty.trim();
+    let (name, args) = match ty.split_once('[') {
+        Some((name, args)) => match args.strip_suffix(']') {
+            Some(args) => (name.trim(), Some(split_top_level(args))),
+            None => return Err(format!("unclosed `[` in type `{ty}`")),
+        },
+        None => (ty, None),
+    };
+
+    let schema = match (name, args.as_deref()) {
+        ("str", None) => json_schema!({"type": "string"}),
+        ("int", None) => json_schema!({"type": "integer"}),
+        ("float", None) => json_schema!({"type": "number"}),
+        ("bool", None) => json_schema!({"type": "boolean"}),
+        ("Any", None) => Schema::from(true),
+        ("Image", None) => schema_for!(Image),
+        ("list", None) => json_schema!({"type": "array"}),
+        ("list", Some([item])) => json_schema!({"type": "array", "items": parse_type(item)?}),
+        ("dict", None) => json_schema!({"type": "object"}),
+        ("dict", Some([key, value])) if key == "str" => json_schema!({
+            "type": "object",
+            "additionalProperties": parse_type(value)?,
+        }),
+        ("Optional", Some([inner])) => json_schema!({
+            "anyOf": [parse_type(inner)?, {"type": "null"}],
+        }),
+        ("Literal", Some(choices)) if !choices.is_empty() => {
+            let choices = choices
+                .iter()
+                .map(|c| parse_literal(c))
+                .collect::<Result<Vec<_>, _>>()?;
+            json_schema!({"enum": choices})
+        }
+        _ => return Err(format!("unsupported type `{ty}`")),
+    };
+    Ok(schema)
+}
+
+/// Parse a `Literal` choice: a quoted string or a JSON scalar.
+fn parse_literal(choice: &str) -> Result<Value, String> {
+    for quote in ['\'', '"'] {
+        if let Some(s) = choice
+            .strip_prefix(quote)
+            .and_then(|c| c.strip_suffix(quote))
+        {
+            return Ok(Value::String(s.to_string()));
+        }
+    }
+    match serde_json::from_str(choice) {
+        Ok(value @ (Value::Number(_) | Value::Bool(_))) => Ok(value),
+        _ => Err(format!("invalid literal `{choice}`")),
+    }
+}
+
+/// Split at the commas outside of brackets and quotes, trimming the parts.
+fn split_top_level(s: &str) -> Vec<String> {
+    let mut parts = vec![];
+    let mut part = String::new();
+    let mut depth = 0usize;
+    let mut quote = None;
+    for c in s.chars() {
+        match (c, quote) {
+            (_, Some(q)) if c == q => quote = None,
+            (_, Some(_)) => {}
+            ('\'' | '"', None) => quote = Some(c),
+            ('[', None) => depth += 1,
+            (']', None) => depth = depth.saturating_sub(1),
+            (',', None) if depth == 0 => {
+                parts.push(part.trim().to_string());
+                part.clear();
+                continue;
+            }
+            _ => {}
+        }
+        part.push(c);
+    }
+    parts.push(part.trim().to_string());
+    parts
+}
+
+/// Returns whether the schema allows `null`, e.g. `Optional[str]`.
+fn is_optional(schema: &Schema) -> bool {
+    schema
+        .get("anyOf")
+        .and_then(Value::as_array)
+        .is_some_and(|branches| {
+            branches
+                .iter()
+                .any(|b| b.get("type") == Some(&"null".into()))
+        })
+}
@@ -0,0 +1,92 @@
+use schemars::{JsonSchema, Schema};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::lm::MessageContent;
+use crate::{Error, field_schemas, media};
+
+/// Prior turns of a conversation, used as an `#[input]` field of a signature.
+///
+/// Adapters expand the turns into user/assistant messages before the current
+/// input, each turn being the input and output of an earlier call of the same
+/// signature.
+///
+/// ```ignore
+/// let output = chat.call(ChatInput { question, history: history.clone() }).await?;
+/// history.push(&input, &output)?;
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[schemars(extend("x-dars-type" = "history"))]
+pub struct History {
+    pub turns: Vec<Turn>,
+}
+
+/// A turn of a [`History`] with the input and output fields by name.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct Turn {
+    pub input: Map<String, Value>,
+    pub output: Map<String, Value>,
+    /// Media of the input (e.g. [`Image`](crate::Image) fields), replaced by
+    /// placeholders in `input` so adapters send them as message parts.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[schemars(skip)]
+    pub media: Vec<MessageContent>,
+}
+
+impl History {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append a turn with the input and output of a call. [`History`] fields of
+    /// the input are left out, the earlier turns being already in the history.
+    pub fn push<I>(&mut self, input: &I, output: &impl Serialize) -> Result<(), Error>
+    where
+        I: Serialize + JsonSchema,
+    {
+        let (input, media) = media::serialize(input)?;
+        let mut input = to_object(input)?;
+        for (name, schema) in field_schemas::<I>() {
+            if Self::is_history(&schema) {
+                input.remove(&name);
+            }
+        }
+        self.turns.push(Turn {
+            input,
+            output: to_object(serde_json::to_value(output)?)?,
+            media,
+        });
+        Ok(())
+    }
+
+    /// Like [`History::push`], but returns the history.
+    pub fn with_turn<I>(mut self, input: &I, output: &impl Serialize) -> Result<Self, Error>
+    where
+        I: Serialize + JsonSchema,
+    {
+        self.push(input, output)?;
+        Ok(self)
+    }
+
+    pub fn len(&self) -> usize {
+        self.turns.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.turns.is_empty()
+    }
+
+    /// Returns whether `schema` is the schema of a [`History`] field.
+    pub fn is_history(schema: &Schema) -> bool {
+        schema.get("x-dars-type").and_then(Value::as_str) == Some("history")
+    }
+}
+
+fn to_object(value: Value) -> Result<Map<String, Value>, Error> {
+    match value {
+        Value::Object(kv) => Ok(kv),
+        value => Err(Error::InvalidArgument(format!(
+            "history turn must be an object, got {value}"
+        ))),
+    }
+}
@@ -1,26 +1,201 @@
-use schemars::JsonSchema;
-use serde::{Deserialize, Deserializer, Serialize, Serializer, de::Error as DeError};
+use std::path::Path;
 
-#[derive(Debug, Clone, JsonSchema)]
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use schemars::{JsonSchema, Schema};
+use serde::ser::SerializeStruct;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::Value;
+
+use crate::lm::MessageContent;
+use crate::{Error, media};
+
+/// An image used as an input or output field.
+///
+/// Output images are generated with [`LM::generate_images`](crate::lm::LM::generate_images)
+/// rather than returned in the JSON output.
+#[derive(Debug, Clone, PartialEq, JsonSchema)]
+#[schemars(extend("x-dars-type" = "image"))]
 pub struct Image {
     #[schemars(description = "Image url or encoded image data")]
     pub url: String,
 }
 
+impl Image {
+    /// Image at a (http or data) url.
+    pub fn from_url(url: impl Into<String>) -> Self {
+        Self { url: url.into() }
+    }
+
+    /// Encode the image data as a data url. The MIME type is detected from the
+    /// content.
+    pub fn from_bytes(bytes: impl AsRef<[u8]>) -> Result<Self, Error> {
+        let bytes = bytes.as_ref();
+        let mime = sniff_mime(bytes).ok_or_else(|| {
+            Error::InvalidArgument("unsupported or unrecognized image format".into())
+        })?;
+        Ok(Self::encode(mime, bytes))
+    }
+
+    /// Read the image file as a data url. The MIME type is detected from the
+    /// content, falling back to the file extension.
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path)?;
+        let mime = sniff_mime(&bytes)
+            .or_else(|| mime_from_extension(path))
+            .ok_or_else(|| {
+                Error::InvalidArgument(format!(
+                    "unsupported or unrecognized image format: {}",
+                    path.display()
+                ))
+            })?;
+        Ok(Self::encode(mime, &bytes))
+    }
+
+    /// Image from base64 encoded data, with or without the `data:` url prefix.
+    pub fn from_base64(data: &str) -> Result<Self, Error> {
+        let data = match data.split_once(";base64,") {
+            Some((prefix, data)) if prefix.starts_with("data:") => data,
+            _ => data,
+        };
+        let bytes = STANDARD
+            .decode(data.trim())
+            .map_err(|e| Error::InvalidArgument(format!("invalid base64 image data: {e}")))?;
+        Self::from_bytes(bytes)
+    }
+
+    /// Returns the MIME type of a data url image.
+    pub fn mime_type(&self) -> Option<&str> {
+        let (prefix, _) = self.url.strip_prefix("data:")?.split_once(";base64,")?;
+        Some(prefix)
+    }
+
+    /// Returns the decoded data of a data url image.
+    pub fn data(&self) -> Option<Vec<u8>> {
+        let (_, data) = self.url.strip_prefix("data:")?.split_once(";base64,")?;
+        STANDARD.decode(data).ok()
+    }
+
+    /// Scale a data url image down so that neither side exceeds `max_dimension`,
+    /// keeping the aspect ratio. Smaller images are returned unchanged.
+    #[cfg(feature = "image")]
+    pub fn downscale(self, max_dimension: u32) -> Result<Self, Error> {
+        use ::image::{ImageFormat, imageops::FilterType};
+
+        let bytes = self.data().ok_or_else(|| {
+            Error::InvalidArgument("only data url images can be downscaled".into())
+        })?;
+        let format = ::image::guess_format(&bytes)
+            .map_err(|e| Error::InvalidArgument(format!("unsupported image: {e}")))?;
+        let img = ::image::load_from_memory_with_format(&bytes, format)
+            .map_err(|e| Error::InvalidArgument(format!("invalid image: {e}")))?;
+        if img.width() <= max_dimension && img.height() <= max_dimension {
+            return Ok(self);
+        }
+
+        // Keep JPEGs lossy, everything else is re-encoded as PNG
+        let format = match format {
+            ImageFormat::Jpeg => ImageFormat::Jpeg,
+            _ => ImageFormat::Png,
+        };
+        let img = img.resize(max_dimension, max_dimension, FilterType::Triangle);
+        let mut buf = std::io::Cursor::new(vec![]);
+        img.write_to(&mut buf, format)
+            .map_err(|e| Error::InvalidArgument(format!("failed to encode image: {e}")))?;
+        Ok(Self::encode(format.to_mime_type(), buf.get_ref()))
+    }
+
+    /// Returns whether `schema` is the schema of an [`Image`] field.
+    pub fn is_image(schema: &Schema) -> bool {
+        schema.get("x-dars-type").and_then(Value::as_str) == Some("image")
+    }
+
+    fn encode(mime: &str, bytes: &[u8]) -> Self {
+        Self {
+            url: format!("data:{mime};base64,{}", STANDARD.encode(bytes)),
+        }
+    }
+}
+
+/// Detect the MIME type of image data from its magic bytes.
+fn sniff_mime(bytes: &[u8]) -> Option<&'static str> {
+    // ISO base media files (HEIC, AVIF) have the brand after `ftyp` at offset 4
+    let brand = bytes.get(4..12);
+
+    if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some("image/png")
+    } else if bytes.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        Some("image/jpeg")
+    } else if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+        Some("image/gif")
+    } else if bytes.starts_with(b"RIFF") && bytes.get(8..12) == Some(b"WEBP") {
+        Some("image/webp")
+    } else if bytes.starts_with(b"BM") {
+        Some("image/bmp")
+    } else if brand == Some(b"ftypheic") || brand == Some(b"ftypheix") {
+        Some("image/heic")
+    } else if brand == Some(b"ftypavif") {
+        Some("image/avif")
+    } else {
+        None
+    }
+}
+
+fn mime_from_extension(path: &Path) -> Option<&'static str> {
+    let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+    let mime = match ext.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "bmp" => "image/bmp",
+        "heic" => "image/heic",
+        "avif" => "image/avif",
+        "svg" => "image/svg+xml",
+        _ => return None,
+    };
+    Some(mime)
+}
+
+/// Serializes as `{"url": ...}`. In adapter inputs the image becomes a
+/// separate part of the message.
 impl Serialize for Image {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: Serializer,
     {
-        serializer.serialize_str(&format!("<dars-img>{}</dars-img>", self.url))
+        media::serialize_media(
+            serializer,
+            || MessageContent::Image {
+                url: self.url.clone(),
+            },
+            |serializer| {
+                let mut image = serializer.serialize_struct("Image", 1)?;
+                image.serialize_field("url", &self.url)?;
+                image.end()
+            },
+        )
     }
 }
 
+/// Accepts a `{"url": ...}` object or a plain url string.
 impl<'de> Deserialize<'de> for Image {
-    fn deserialize<D>(_de: D) -> Result<Self, D::Error>
+    fn deserialize<D>(de: D) -> Result<Self, D::Error>
     where
         D: Deserializer<'de>,
     {
-        Err(D::Error::custom("Deserializing image is not supported"))
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Repr {
+            Url(String),
+            Object { url: String },
+        }
+
+        let url = match Repr::deserialize(de)? {
+            Repr::Url(url) => url,
+            Repr::Object { url } => url,
+        };
+        Ok(Self { url })
     }
 }
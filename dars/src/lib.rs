@@ -8,17 +8,43 @@ pub use dars_macros::*;
 mod signature;
 pub use signature::*;
 
+mod dynamic;
+pub use dynamic::DynamicSignature;
+
 pub mod model;
 pub use model::*;
 
 mod module;
 pub use module::*;
 
+pub mod callback;
+pub use callback::{Callback, traced};
+
+pub mod otel;
+
+mod context;
+pub use context::{CallContext, Scoped};
+
+mod audio;
+pub use audio::Audio;
+
+mod document;
+pub use document::{Document, DocumentContent, Page};
+
+mod history;
+pub use history::{History, Turn};
+
 mod image;
 pub use image::Image;
 
+mod media;
+
 pub mod adapter;
 pub mod lm;
+pub mod testing;
+
+pub mod validate;
+pub use validate::Violation;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Field {
@@ -35,9 +61,28 @@ pub enum Error {
     #[error("OpenAI: {0}")]
     OpenAI(#[from] async_openai::error::OpenAIError),
 
+    #[error("io: {0}")]
+    Io(#[from] std::io::Error),
+
     #[error("invalid argument: {0}")]
     InvalidArgument(String),
 
     #[error("model call failed: {0}")]
     ModelCall(String),
+
+    /// The LM does not accept a content part of the messages, e.g. a file.
+    #[error("unsupported content: {0}")]
+    UnsupportedContent(String),
+
+    #[error("missing output fields: {}", .0.join(", "))]
+    MissingFields(Vec<String>),
+
+    #[error("output validation failed: {}", .0.iter().map(ToString::to_string).collect::<Vec<_>>().join("; "))]
+    Validation(Vec<Violation>),
+
+    #[error("assertion failed: {0}")]
+    Assertion(String),
+
+    #[error("call cancelled")]
+    Cancelled,
 }
@@ -0,0 +1,117 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use schemars::Schema;
+use tracing::warn;
+
+use crate::Error;
+use crate::lm::{LM, Message, MessageContent, is_rate_limit};
+
+/// Broad classes of LM errors used to decide on failover.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorClass {
+    /// The backend rejected the call because of rate limits.
+    RateLimit,
+    /// The backend could not be reached or failed on its side.
+    Unavailable,
+    /// The request was rejected as invalid.
+    InvalidRequest,
+    /// The response could not be read.
+    InvalidResponse,
+    /// Any other error.
+    Other,
+}
+
+impl ErrorClass {
+    /// Classify an error returned by an LM.
+    pub fn of(err: &Error) -> Self {
+        if is_rate_limit(err) {
+            return ErrorClass::RateLimit;
+        }
+
+        match err {
+            Error::ModelCall(_) => ErrorClass::Unavailable,
+            Error::InvalidArgument(_) | Error::UnsupportedContent(_) => ErrorClass::InvalidRequest,
+            Error::SerdeJson(_) => ErrorClass::InvalidResponse,
+            #[cfg(feature = "openai")]
+            Error::OpenAI(e) => {
+                use async_openai::error::OpenAIError;
+
+                match e {
+                    OpenAIError::Reqwest(_) | OpenAIError::StreamError(_) => {
+                        ErrorClass::Unavailable
+                    }
+                    OpenAIError::ApiError(e) => match e.r#type.as_deref() {
+                        Some("server_error") => ErrorClass::Unavailable,
+                        Some("invalid_request_error") => ErrorClass::InvalidRequest,
+                        _ => ErrorClass::Other,
+                    },
+                    OpenAIError::JSONDeserialize(..) => ErrorClass::InvalidResponse,
+                    OpenAIError::InvalidArgument(_) => ErrorClass::InvalidRequest,
+                    _ => ErrorClass::Other,
+                }
+            }
+            _ => ErrorClass::Other,
+        }
+    }
+}
+
+/// LM trying the backends in order, failing over to the next one on errors of
+/// the configured classes ([`ErrorClass::RateLimit`] and
+/// [`ErrorClass::Unavailable`] by default).
+pub struct FallbackLM {
+    lms: Vec<Arc<dyn LM>>,
+    failover_on: Vec<ErrorClass>,
+}
+
+impl FallbackLM {
+    pub fn new(lms: Vec<Arc<dyn LM>>) -> Self {
+        Self {
+            lms,
+            failover_on: vec![ErrorClass::RateLimit, ErrorClass::Unavailable],
+        }
+    }
+
+    /// Set the error classes that trigger failover to the next backend.
+    pub fn with_failover_on(mut self, classes: impl IntoIterator<Item = ErrorClass>) -> Self {
+        self.failover_on = classes.into_iter().collect();
+        self
+    }
+}
+
+#[async_trait]
+impl LM for FallbackLM {
+    async fn call(&self, messages: Vec<Message>, schema: Option<Schema>) -> Result<String, Error> {
+        self.try_in_order(|lm| lm.call(messages.clone(), schema.clone()))
+            .await
+    }
+
+    async fn generate_images(
+        &self,
+        messages: Vec<Message>,
+        n: usize,
+    ) -> Result<Vec<MessageContent>, Error> {
+        self.try_in_order(|lm| lm.generate_images(messages.clone(), n))
+            .await
+    }
+}
+
+impl FallbackLM {
+    async fn try_in_order<'a, T, F>(&'a self, f: impl Fn(&'a dyn LM) -> F) -> Result<T, Error>
+    where
+        F: Future<Output = Result<T, Error>>,
+    {
+        let mut last_err = None;
+        for (i, lm) in self.lms.iter().enumerate() {
+            match f(lm.as_ref()).await {
+                Err(e) if self.failover_on.contains(&ErrorClass::of(&e)) => {
+                    warn!("LM {i} failed, falling back to the next one: {e}");
+                    last_err = Some(e);
+                }
+                result => return result,
+            }
+        }
+
+        Err(last_err.unwrap_or(Error::InvalidArgument("FallbackLM without LMs".into())))
+    }
+}
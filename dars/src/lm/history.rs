@@ -0,0 +1,156 @@
+use std::collections::VecDeque;
+use std::fmt::Write;
+use std::sync::{Mutex, OnceLock};
+
+use crate::lm::Message;
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const BLUE: &str = "\x1b[34m";
+const GREEN: &str = "\x1b[32m";
+const MAGENTA: &str = "\x1b[35m";
+const YELLOW: &str = "\x1b[33m";
+
+/// An LM call and its completion.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Interaction {
+    pub messages: Vec<Message>,
+    pub completion: String,
+}
+
+/// Bounded log of LM interactions, oldest first.
+///
+/// LM calls made by modules are recorded in the global [`history`] once it is
+/// enabled with [`InteractionLog::set_capacity`]. Not to be confused with
+/// [`History`](crate::History), the conversation input type.
+pub struct InteractionLog {
+    capacity: Mutex<usize>,
+    entries: Mutex<VecDeque<Interaction>>,
+}
+
+impl InteractionLog {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: Mutex::new(capacity),
+            entries: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// Set the maximum number of interactions kept. Zero disables the history.
+    pub fn set_capacity(&self, capacity: usize) {
+        *self.capacity.lock().unwrap() = capacity;
+        let mut entries = self.entries.lock().unwrap();
+        while entries.len() > capacity {
+            entries.pop_front();
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        *self.capacity.lock().unwrap() > 0
+    }
+
+    /// Record an interaction, dropping the oldest one if the history is full.
+    pub fn record(&self, messages: Vec<Message>, completion: impl Into<String>) {
+        let capacity = *self.capacity.lock().unwrap();
+        if capacity == 0 {
+            return;
+        }
+
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() == capacity {
+            entries.pop_front();
+        }
+        entries.push_back(Interaction {
+            messages,
+            completion: completion.into(),
+        });
+    }
+
+    /// Returns the last `n` interactions, oldest first.
+    pub fn last(&self, n: usize) -> Vec<Interaction> {
+        let entries = self.entries.lock().unwrap();
+        entries
+            .iter()
+            .skip(entries.len().saturating_sub(n))
+            .cloned()
+            .collect()
+    }
+
+    pub fn clear(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+
+    /// Render the last `n` interactions, oldest first.
+    pub fn render(&self, n: usize, options: InspectOptions) -> String {
+        let interactions = self.last(n);
+        let paint = |color: &str, text: &str| {
+            if options.colors {
+                format!("{color}{text}{RESET}")
+            } else {
+                text.to_string()
+            }
+        };
+
+        let mut buf = String::new();
+        for (i, interaction) in interactions.iter().enumerate() {
+            let header = format!("[{}/{}]", i + 1, interactions.len());
+            writeln!(buf, "\n{}\n", paint(BOLD, &header)).unwrap();
+
+            for message in &interaction.messages {
+                let color = match message {
+                    Message::System { .. } => MAGENTA,
+                    Message::User { .. } => BLUE,
+                    Message::Assistant { .. } => YELLOW,
+                };
+                // `{:#}` shows the full image urls
+                let text = if options.full_images {
+                    format!("{message:#}")
+                } else {
+                    format!("{message}")
+                };
+                let (role, content) = text.split_once('\n').unwrap_or((&text, ""));
+                writeln!(buf, "{}", paint(color, role)).unwrap();
+                writeln!(buf, "{}\n", content.trim_end()).unwrap();
+            }
+
+            writeln!(buf, "{}", paint(GREEN, "Response:")).unwrap();
+            writeln!(buf, "{}", paint(GREEN, &interaction.completion)).unwrap();
+        }
+        buf
+    }
+}
+
+/// Options of [`InteractionLog::render`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct InspectOptions {
+    /// Color the roles and completions with ANSI escape codes.
+    pub colors: bool,
+    /// Show image urls in full instead of their length.
+    pub full_images: bool,
+}
+
+impl InspectOptions {
+    pub fn with_colors(mut self, colors: bool) -> Self {
+        self.colors = colors;
+        self
+    }
+
+    pub fn with_full_images(mut self, full_images: bool) -> Self {
+        self.full_images = full_images;
+        self
+    }
+}
+
+/// Returns the global history of LM calls made by modules.
+///
+/// The history is disabled by default, as it keeps the full messages including
+/// media data. Enable it with e.g. `history().set_capacity(100)`.
+pub fn history() -> &'static InteractionLog {
+    static HISTORY: OnceLock<InteractionLog> = OnceLock::new();
+    HISTORY.get_or_init(|| InteractionLog::new(0))
+}
+
+/// Print the last `n` LM interactions of the global [`history`].
+pub fn inspect_history(n: usize) {
+    print!("{}", history().render(n, InspectOptions::default()));
+}
@@ -2,12 +2,22 @@ use std::fmt::Display;
 
 use async_trait::async_trait;
 use schemars::Schema;
+use serde::{Deserialize, Serialize};
 
 use crate::Error;
 
 #[cfg(feature = "openai")]
 pub mod openai;
 
+mod fallback;
+mod history;
+mod rate_limit;
+mod router;
+pub use fallback::{ErrorClass, FallbackLM};
+pub use history::{InspectOptions, Interaction, InteractionLog, history, inspect_history};
+pub use rate_limit::{RateLimitedLM, RateLimits, is_rate_limit, retry_after};
+pub use router::{RoutePredicate, RouterLM};
+
 #[async_trait]
 pub trait LM
 where
@@ -15,42 +25,120 @@ where
 {
     /// Call the LM with the given input messages and an optional json schema for the output.
     async fn call(&self, message: Vec<Message>, schema: Option<Schema>) -> Result<String, Error>;
+
+    /// Generate `n` images described by the messages, returned as
+    /// [`MessageContent::Image`] parts. Used for signatures with
+    /// [`Image`](crate::Image) outputs.
+    async fn generate_images(
+        &self,
+        messages: Vec<Message>,
+        n: usize,
+    ) -> Result<Vec<MessageContent>, Error> {
+        let _ = (messages, n);
+        Err(Error::InvalidArgument(
+            "LM does not support image generation".into(),
+        ))
+    }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "role", rename_all = "lowercase")]
 pub enum Message {
     System { instruction: String },
     User { content: Vec<MessageContent> },
     Assistant { content: MessageContent },
 }
 
+/// The alternate form (`{:#}`) shows image urls in full.
 impl Display for Message {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Message::System { instruction } => write!(f, "System:\n{}", instruction),
             Message::User { content } => {
-                write!(f, "User:\n")?;
+                writeln!(f, "User:")?;
                 for c in content.iter() {
-                    write!(f, "{}\n", c)?;
+                    c.fmt(f)?;
+                    writeln!(f)?;
                 }
                 Ok(())
             }
-            Message::Assistant { content } => write!(f, "Assistant:\n{}", content),
+            Message::Assistant { content } => {
+                writeln!(f, "Assistant:")?;
+                content.fmt(f)
+            }
         }
     }
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
 pub enum MessageContent {
-    Text { text: String },
-    Image { url: String },
+    Text {
+        text: String,
+    },
+    Image {
+        url: String,
+    },
+    /// Base64 encoded audio in the given format (e.g. `wav`).
+    Audio {
+        data: String,
+        format: String,
+    },
+    /// Base64 encoded file, e.g. a PDF.
+    File {
+        data: String,
+        mime_type: String,
+        filename: Option<String>,
+    },
 }
 
+/// The alternate form (`{:#}`) shows image urls in full.
 impl Display for MessageContent {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             MessageContent::Text { text } => write!(f, "{}", text),
+            MessageContent::Image { url } if f.alternate() => write!(f, "<image url={}>", url),
             MessageContent::Image { url } => write!(f, "<image len={}>", url.len()),
+            MessageContent::Audio { data, format } if f.alternate() => {
+                write!(f, "<audio format={} data={}>", format, data)
+            }
+            MessageContent::Audio { data, format } => {
+                write!(f, "<audio format={} len={}>", format, data.len())
+            }
+            MessageContent::File {
+                data, mime_type, ..
+            } if f.alternate() => write!(f, "<file type={} data={}>", mime_type, data),
+            MessageContent::File {
+                data, mime_type, ..
+            } => write!(f, "<file type={} len={}>", mime_type, data.len()),
         }
     }
 }
+
+/// Tokens counted for an image in [`estimate_tokens`].
+const IMAGE_TOKENS: u32 = 85;
+
+/// Tokens counted per KB of base64 audio data in [`estimate_tokens`].
+const AUDIO_TOKENS_PER_KB: u32 = 4;
+
+/// Tokens counted per KB of base64 file data in [`estimate_tokens`].
+const FILE_TOKENS_PER_KB: u32 = 20;
+
+/// Rough token estimate of the messages (~4 characters per token).
+pub fn estimate_tokens(messages: &[Message]) -> u32 {
+    let content_tokens = |c: &MessageContent| match c {
+        MessageContent::Text { text } => text.len() as u32 / 4,
+        MessageContent::Image { .. } => IMAGE_TOKENS,
+        MessageContent::Audio { data, .. } => data.len() as u32 / 1024 * AUDIO_TOKENS_PER_KB,
+        MessageContent::File { data, .. } => data.len() as u32 / 1024 * FILE_TOKENS_PER_KB,
+    };
+
+    messages
+        .iter()
+        .map(|m| match m {
+            Message::System { instruction } => instruction.len() as u32 / 4,
+            Message::User { content } => content.iter().map(content_tokens).sum(),
+            Message::Assistant { content } => content_tokens(content),
+        })
+        .sum()
+}
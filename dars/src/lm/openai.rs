@@ -2,18 +2,22 @@ use async_openai::{
     Client,
     config::Config,
     types::chat::{
-        ChatCompletionRequestMessage, ChatCompletionRequestUserMessage,
+        ChatCompletionRequestMessage, ChatCompletionRequestMessageContentPartAudio,
+        ChatCompletionRequestMessageContentPartFile, ChatCompletionRequestUserMessage,
         ChatCompletionRequestUserMessageContent, ChatCompletionRequestUserMessageContentPart,
-        CreateChatCompletionRequest, ImageDetail, ImageUrl, ReasoningEffort, ResponseFormat,
-        ResponseFormatJsonSchema,
+        CreateChatCompletionRequest, ImageDetail, ImageUrl, InputAudio, InputAudioFormat,
+        ReasoningEffort, ResponseFormat, ResponseFormatJsonSchema,
     },
+    types::images::{CreateImageRequest, Image as GeneratedImage, ImageModel},
 };
 use async_trait::async_trait;
 use schemars::Schema;
 use tracing::debug;
 
+#[cfg(feature = "otel")]
+use crate::otel;
 use crate::{
-    Error,
+    CallContext, Error,
     lm::{LM, Message, MessageContent},
 };
 
@@ -25,6 +29,8 @@ pub struct ModelConfig {
     pub top_p: Option<f32>,
     pub reasoning_effort: Option<ReasoningEffort>,
     pub json_schema: bool,
+    /// Model generating image outputs, `gpt-image-1` by default.
+    pub image_model: Option<String>,
 }
 
 impl ModelConfig {
@@ -34,6 +40,11 @@ impl ModelConfig {
             ..Default::default()
         }
     }
+
+    pub fn with_image_model(mut self, model: impl Into<String>) -> Self {
+        self.image_model = Some(model.into());
+        self
+    }
 }
 
 /// LM client for providers that support OpenAI API.
@@ -54,10 +65,11 @@ impl<C: Config> OpenAILM<C> {
 #[async_trait]
 impl<C: Config + 'static> LM for OpenAILM<C> {
     async fn call(&self, messages: Vec<Message>, schema: Option<Schema>) -> Result<String, Error> {
+        let ctx = CallContext::current();
         let mut req = CreateChatCompletionRequest {
             messages: vec![],
             model: self.model_config.model.clone(),
-            temperature: self.model_config.temperature,
+            temperature: ctx.temperature.or(self.model_config.temperature),
             max_completion_tokens: self.model_config.max_tokens,
             top_p: self.model_config.top_p,
             reasoning_effort: self.model_config.reasoning_effort.clone(),
@@ -74,16 +86,112 @@ impl<C: Config + 'static> LM for OpenAILM<C> {
             req.messages.push(m.try_into()?);
         }
 
+        #[cfg(feature = "otel")]
+        otel::record_request(otel::Request {
+            provider: "openai",
+            model: &req.model,
+            temperature: req.temperature,
+            max_tokens: req.max_completion_tokens,
+            top_p: req.top_p,
+        });
+
         // Call the API
         debug!("ChatCompletionRequest: {:#?}", req);
         let resp = self.client.chat().create(req).await?;
         debug!("ChatCompletionResponse: {:#?}", resp);
 
+        #[cfg(feature = "otel")]
+        otel::record_response(otel::Response {
+            id: &resp.id,
+            model: &resp.model,
+            finish_reasons: resp
+                .choices
+                .iter()
+                .filter_map(|c| c.finish_reason.as_ref())
+                .filter_map(|r| serde_json::to_value(r).ok()?.as_str().map(str::to_string))
+                .collect(),
+            input_tokens: resp.usage.as_ref().map(|u| u.prompt_tokens),
+            output_tokens: resp.usage.as_ref().map(|u| u.completion_tokens),
+        });
+
         // Get the first response message
         let content = resp.choices[0].message.content.clone().unwrap_or_default();
 
         Ok(content)
     }
+
+    /// Generates the images with the images API, prompted with the text of the messages.
+    async fn generate_images(
+        &self,
+        messages: Vec<Message>,
+        n: usize,
+    ) -> Result<Vec<MessageContent>, Error> {
+        let model = self
+            .model_config
+            .image_model
+            .clone()
+            .unwrap_or_else(|| DEFAULT_IMAGE_MODEL.to_string());
+        let n = u8::try_from(n)
+            .map_err(|_| Error::InvalidArgument(format!("too many images requested: {n}")))?;
+        let req = CreateImageRequest {
+            prompt: image_prompt(&messages),
+            model: Some(ImageModel::Other(model.clone())),
+            n: Some(n),
+            ..Default::default()
+        };
+
+        #[cfg(feature = "otel")]
+        otel::record_request(otel::Request {
+            provider: "openai",
+            model: &model,
+            ..Default::default()
+        });
+
+        debug!("CreateImageRequest: {:#?}", req);
+        let resp = self.client.images().generate(req).await?;
+
+        // `gpt-image-1` returns base64 data in the requested format, PNG by default
+        let format = resp
+            .output_format
+            .as_ref()
+            .and_then(|f| serde_json::to_value(f).ok()?.as_str().map(str::to_string))
+            .unwrap_or_else(|| "png".to_string());
+        let images = resp
+            .data
+            .iter()
+            .map(|image| match image.as_ref() {
+                GeneratedImage::Url { url, .. } => MessageContent::Image { url: url.clone() },
+                GeneratedImage::B64Json { b64_json, .. } => MessageContent::Image {
+                    url: format!("data:image/{format};base64,{b64_json}"),
+                },
+            })
+            .collect();
+        Ok(images)
+    }
+}
+
+/// Default [`ModelConfig::image_model`].
+const DEFAULT_IMAGE_MODEL: &str = "gpt-image-1";
+
+/// Text of the messages as an image prompt. Images API prompts are plain text,
+/// so non-text parts are left out.
+fn image_prompt(messages: &[Message]) -> String {
+    let text = |c: &MessageContent| match c {
+        MessageContent::Text { text } => Some(text.clone()),
+        _ => None,
+    };
+    messages
+        .iter()
+        .filter_map(|m| match m {
+            Message::System { instruction } => Some(instruction.clone()),
+            Message::User { content } => {
+                let parts = content.iter().filter_map(text).collect::<Vec<_>>();
+                (!parts.is_empty()).then(|| parts.concat())
+            }
+            Message::Assistant { content } => text(content),
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
 }
 
 impl TryFrom<Message> for ChatCompletionRequestMessage {
@@ -110,20 +218,31 @@ impl TryFrom<Message> for ChatCompletionRequestMessage {
                                 .into(),
                             )
                         }
+                        MessageContent::Audio { data, format } => input_audio(data, &format)?,
+                        MessageContent::File {
+                            data,
+                            mime_type,
+                            filename,
+                        } => input_file(data, &mime_type, filename)?,
                     };
 
                     parts.push(part);
                 }
-                ChatCompletionRequestMessage::User(ChatCompletionRequestUserMessage {
-                    name: None,
-                    content: ChatCompletionRequestUserMessageContent::Array(parts),
-                })
+                user_message(parts)
             }
             Message::Assistant { content } => match content {
                 MessageContent::Text { text } => {
                     ChatCompletionRequestMessage::Assistant(text.into())
                 }
                 MessageContent::Image { url } => image_message(url),
+                MessageContent::Audio { data, format } => {
+                    user_message(vec![input_audio(data, &format)?])
+                }
+                MessageContent::File {
+                    data,
+                    mime_type,
+                    filename,
+                } => user_message(vec![input_file(data, &mime_type, filename)?]),
             },
         };
 
@@ -142,6 +261,56 @@ fn image_message(url: String) -> ChatCompletionRequestMessage {
     })
 }
 
+fn input_audio(
+    data: String,
+    format: &str,
+) -> Result<ChatCompletionRequestUserMessageContentPart, Error> {
+    let format = match format {
+        "wav" => InputAudioFormat::Wav,
+        "mp3" => InputAudioFormat::Mp3,
+        format => {
+            return Err(Error::InvalidArgument(format!(
+                "OpenAI supports only wav and mp3 audio input, got {format}"
+            )));
+        }
+    };
+    Ok(ChatCompletionRequestUserMessageContentPart::InputAudio(
+        ChatCompletionRequestMessageContentPartAudio {
+            input_audio: InputAudio { data, format },
+        },
+    ))
+}
+
+fn input_file(
+    data: String,
+    mime_type: &str,
+    filename: Option<String>,
+) -> Result<ChatCompletionRequestUserMessageContentPart, Error> {
+    if mime_type != "application/pdf" {
+        return Err(Error::UnsupportedContent(format!(
+            "OpenAI supports only PDF file input, got {mime_type}"
+        )));
+    }
+
+    // `FileObject` has private fields and no builder
+    let file = serde_json::from_value(serde_json::json!({
+        "file_data": format!("data:{mime_type};base64,{data}"),
+        "filename": filename.unwrap_or_else(|| "document.pdf".to_string()),
+    }))?;
+    Ok(ChatCompletionRequestUserMessageContentPart::File(
+        ChatCompletionRequestMessageContentPartFile { file },
+    ))
+}
+
+fn user_message(
+    parts: Vec<ChatCompletionRequestUserMessageContentPart>,
+) -> ChatCompletionRequestMessage {
+    ChatCompletionRequestMessage::User(ChatCompletionRequestUserMessage {
+        name: None,
+        content: ChatCompletionRequestUserMessageContent::Array(parts),
+    })
+}
+
 fn convert_schema_to_response_format(schema: Schema) -> ResponseFormat {
     ResponseFormat::JsonSchema {
         json_schema: ResponseFormatJsonSchema {
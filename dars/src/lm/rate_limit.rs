@@ -0,0 +1,323 @@
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use regex::Regex;
+use schemars::Schema;
+use tokio::sync::Semaphore;
+use tokio::time::{Instant, sleep_until};
+use tracing::warn;
+
+use crate::Error;
+use crate::lm::{LM, Message, MessageContent, estimate_tokens};
+
+/// Initial wait after a rate limit error without a retry hint, doubled on each retry.
+const DEFAULT_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Maximum wait after a rate limit error, also bounding the retry hints of the backend.
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// Limits enforced by [`RateLimitedLM`].
+#[derive(Debug, Clone)]
+pub struct RateLimits {
+    /// Maximum number of requests per minute.
+    pub requests_per_minute: Option<u32>,
+    /// Maximum number of estimated tokens per minute.
+    pub tokens_per_minute: Option<u32>,
+    /// Tokens added to the prompt estimate of each request to account for the completion.
+    pub completion_tokens: u32,
+    /// Maximum number of requests in flight.
+    pub max_in_flight: Option<usize>,
+    /// Number of retries after a rate limit error.
+    pub max_retries: usize,
+}
+
+impl Default for RateLimits {
+    fn default() -> Self {
+        Self {
+            requests_per_minute: None,
+            tokens_per_minute: None,
+            completion_tokens: 0,
+            max_in_flight: None,
+            max_retries: 3,
+        }
+    }
+}
+
+impl RateLimits {
+    pub fn with_requests_per_minute(mut self, rpm: u32) -> Self {
+        self.requests_per_minute = Some(rpm);
+        self
+    }
+
+    pub fn with_tokens_per_minute(mut self, tpm: u32) -> Self {
+        self.tokens_per_minute = Some(tpm);
+        self
+    }
+
+    pub fn with_completion_tokens(mut self, tokens: u32) -> Self {
+        self.completion_tokens = tokens;
+        self
+    }
+
+    pub fn with_max_in_flight(mut self, max_in_flight: usize) -> Self {
+        self.max_in_flight = Some(max_in_flight);
+        self
+    }
+
+    pub fn with_max_retries(mut self, max_retries: usize) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+}
+
+/// LM wrapper enforcing request and token rate limits on any backend.
+///
+/// Requests wait for capacity in token buckets refilled continuously over a
+/// minute. Rate limit errors of the backend pause all requests for the duration
+/// hinted by the error (see [`retry_after`]) before retrying.
+pub struct RateLimitedLM {
+    lm: Arc<dyn LM>,
+    limits: RateLimits,
+    in_flight: Option<Semaphore>,
+    state: Mutex<State>,
+}
+
+struct State {
+    requests: Option<Bucket>,
+    tokens: Option<Bucket>,
+    blocked_until: Option<Instant>,
+}
+
+impl RateLimitedLM {
+    pub fn new(lm: Arc<dyn LM>, limits: RateLimits) -> Self {
+        let now = Instant::now();
+        Self {
+            lm,
+            in_flight: limits.max_in_flight.map(Semaphore::new),
+            state: Mutex::new(State {
+                requests: limits.requests_per_minute.map(|rpm| Bucket::new(rpm, now)),
+                tokens: limits.tokens_per_minute.map(|tpm| Bucket::new(tpm, now)),
+                blocked_until: None,
+            }),
+            limits,
+        }
+    }
+
+    /// Wait until both buckets have capacity for a request of `tokens` tokens.
+    async fn acquire(&self, tokens: u32) {
+        loop {
+            let wait_until = {
+                let mut guard = self.state.lock().unwrap();
+                let state = &mut *guard;
+                let now = Instant::now();
+
+                let mut wait_until = state.blocked_until.filter(|t| *t > now);
+                for (bucket, amount) in [(&mut state.requests, 1), (&mut state.tokens, tokens)] {
+                    if let Some(bucket) = bucket {
+                        bucket.refill(now);
+                        if let Some(ready) = bucket.ready_at(amount, now) {
+                            wait_until = wait_until.max(Some(ready));
+                        }
+                    }
+                }
+
+                if wait_until.is_none() {
+                    for (bucket, amount) in [(&mut state.requests, 1), (&mut state.tokens, tokens)]
+                    {
+                        if let Some(bucket) = bucket {
+                            bucket.take(amount);
+                        }
+                    }
+                    return;
+                }
+                wait_until
+            };
+
+            if let Some(t) = wait_until {
+                sleep_until(t).await;
+            }
+        }
+    }
+
+    fn block_for(&self, duration: Duration) {
+        let until = Instant::now() + duration;
+        let mut state = self.state.lock().unwrap();
+        state.blocked_until = state.blocked_until.max(Some(until));
+    }
+}
+
+#[async_trait]
+impl LM for RateLimitedLM {
+    async fn call(&self, messages: Vec<Message>, schema: Option<Schema>) -> Result<String, Error> {
+        self.limited(&messages, || self.lm.call(messages.clone(), schema.clone()))
+            .await
+    }
+
+    async fn generate_images(
+        &self,
+        messages: Vec<Message>,
+        n: usize,
+    ) -> Result<Vec<MessageContent>, Error> {
+        self.limited(&messages, || self.lm.generate_images(messages.clone(), n))
+            .await
+    }
+}
+
+impl RateLimitedLM {
+    /// Run `f` within the limits, retrying after rate limit errors.
+    async fn limited<T, F>(&self, messages: &[Message], f: impl Fn() -> F) -> Result<T, Error>
+    where
+        F: Future<Output = Result<T, Error>>,
+    {
+        let _permit = match &self.in_flight {
+            Some(semaphore) => Some(
+                semaphore
+                    .acquire()
+                    .await
+                    .expect("semaphore is never closed"),
+            ),
+            None => None,
+        };
+
+        let tokens = estimate_tokens(messages).saturating_add(self.limits.completion_tokens);
+        let mut backoff = DEFAULT_BACKOFF;
+        for attempt in 0..=self.limits.max_retries {
+            self.acquire(tokens).await;
+
+            match f().await {
+                Err(e) if attempt < self.limits.max_retries && is_rate_limit(&e) => {
+                    let wait = retry_after(&e).unwrap_or(backoff);
+                    warn!("Rate limited, retrying in {wait:?}: {e}");
+                    self.block_for(wait);
+                    backoff = (backoff * 2).min(MAX_BACKOFF);
+                }
+                result => return result,
+            }
+        }
+
+        unreachable!("the last attempt always returns")
+    }
+}
+
+/// Token bucket refilled continuously with `capacity` tokens per minute.
+struct Bucket {
+    capacity: f64,
+    available: f64,
+    updated: Instant,
+}
+
+impl Bucket {
+    fn new(per_minute: u32, now: Instant) -> Self {
+        Self {
+            capacity: per_minute as f64,
+            available: per_minute as f64,
+            updated: now,
+        }
+    }
+
+    fn rate_per_sec(&self) -> f64 {
+        self.capacity / 60.0
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.available = (self.available + elapsed * self.rate_per_sec()).min(self.capacity);
+        self.updated = now;
+    }
+
+    /// Returns when `amount` will be available, or `None` if it is available now.
+    /// Requests larger than the capacity only wait for a full bucket.
+    fn ready_at(&self, amount: u32, now: Instant) -> Option<Instant> {
+        let amount = (amount as f64).min(self.capacity);
+        // Tolerate rounding of the refill
+        if self.available + 1e-6 >= amount {
+            return None;
+        }
+        let missing = amount - self.available;
+        Some(now + Duration::from_secs_f64(missing / self.rate_per_sec()))
+    }
+
+    fn take(&mut self, amount: u32) {
+        self.available = (self.available - (amount as f64).min(self.capacity)).max(0.0);
+    }
+}
+
+/// Returns true if the error is a rate limit error of the backend.
+pub fn is_rate_limit(err: &Error) -> bool {
+    match err {
+        #[cfg(feature = "openai")]
+        Error::OpenAI(async_openai::error::OpenAIError::ApiError(e)) => {
+            e.code.as_deref() == Some("rate_limit_exceeded")
+                || matches!(e.r#type.as_deref(), Some("requests" | "tokens"))
+                || e.message.starts_with("Rate limit")
+        }
+        _ => false,
+    }
+}
+
+/// Returns the wait time hinted by a rate limit error, e.g. `Please try again in 1.5s`.
+pub fn retry_after(err: &Error) -> Option<Duration> {
+    match err {
+        #[cfg(feature = "openai")]
+        Error::OpenAI(async_openai::error::OpenAIError::ApiError(e)) => {
+            parse_retry_after(&e.message)
+        }
+        _ => None,
+    }
+}
+
+#[cfg_attr(not(feature = "openai"), allow(dead_code))]
+fn parse_retry_after(message: &str) -> Option<Duration> {
+    static RETRY_AFTER: LazyLock<Regex> = LazyLock::new(|| {
+        const NUMBER: &str = r"-?[0-9.]+(?:e[+-]?[0-9]+)?";
+        Regex::new(&format!(
+            r"(?i)(?:try again in|retry after)\s+({NUMBER})\s*(ms|s|seconds?)\b|retry-after:\s*({NUMBER})\b"
+        ))
+        .unwrap()
+    });
+    let caps = RETRY_AFTER.captures(message)?;
+    let (value, unit) = match caps.get(1) {
+        Some(value) => (value, caps.get(2).map(|u| u.as_str())),
+        // `Retry-After` header, in seconds
+        None => (caps.get(3)?, None),
+    };
+    let value = value.as_str().parse::<f64>().ok()?;
+    let secs = match unit {
+        Some("ms") => value / 1000.0,
+        _ => value,
+    };
+    if !secs.is_finite() || secs < 0.0 {
+        return None;
+    }
+    // Finite and positive, so only overflows past the maximum
+    Some(Duration::try_from_secs_f64(secs).map_or(MAX_BACKOFF, |d| d.min(MAX_BACKOFF)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(
+        "Rate limit reached for gpt-4o-mini on tokens per min (TPM): Limit 200000, Used 199000, Requested 2000. Please try again in 300ms.",
+        Some(Duration::from_millis(300))
+    )]
+    #[case(
+        "Rate limit reached on requests per min (RPM). Please try again in 1.5s.",
+        Some(Duration::from_millis(1500))
+    )]
+    #[case("Please retry after 20 seconds", Some(Duration::from_secs(20)))]
+    #[case("Retry-After: 20", Some(Duration::from_secs(20)))]
+    #[case("Retry-After: 1e30", Some(MAX_BACKOFF))]
+    #[case("Please try again in 1e30s.", Some(MAX_BACKOFF))]
+    #[case("Please try again in 3600s.", Some(MAX_BACKOFF))]
+    #[case("Please try again in -5s.", None)]
+    #[case("Retry-After: -1", None)]
+    #[case("Please try again in 1.2.3s.", None)]
+    #[case("Internal server error", None)]
+    fn test_parse_retry_after(#[case] message: &str, #[case] expected: Option<Duration>) {
+        assert_eq!(parse_retry_after(message), expected);
+    }
+}
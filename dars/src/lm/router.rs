@@ -0,0 +1,79 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use schemars::Schema;
+use tracing::debug;
+
+use crate::Error;
+use crate::lm::{LM, Message, MessageContent};
+
+/// Predicate selecting a route for the messages and output schema of a call.
+pub type RoutePredicate = Box<dyn Fn(&[Message], Option<&Schema>) -> bool + Send + Sync>;
+
+/// LM selecting a backend per call. Routes are checked in the order they were
+/// added and the first matching one is used, otherwise the call goes to the
+/// default backend.
+///
+/// ```ignore
+/// let lm = RouterLM::new(mini).route(|messages, _| estimate_tokens(messages) > 100_000, long_context);
+/// ```
+pub struct RouterLM {
+    routes: Vec<(RoutePredicate, Arc<dyn LM>)>,
+    default: Arc<dyn LM>,
+}
+
+impl RouterLM {
+    pub fn new(default: Arc<dyn LM>) -> Self {
+        Self {
+            routes: vec![],
+            default,
+        }
+    }
+
+    /// Route calls matching `predicate` to `lm`.
+    pub fn route(
+        mut self,
+        predicate: impl Fn(&[Message], Option<&Schema>) -> bool + Send + Sync + 'static,
+        lm: Arc<dyn LM>,
+    ) -> Self {
+        self.routes.push((Box::new(predicate), lm));
+        self
+    }
+}
+
+#[async_trait]
+impl LM for RouterLM {
+    async fn call(&self, messages: Vec<Message>, schema: Option<Schema>) -> Result<String, Error> {
+        self.select(&messages, schema.as_ref())
+            .call(messages, schema)
+            .await
+    }
+
+    /// Routes image generation as a call without an output schema.
+    async fn generate_images(
+        &self,
+        messages: Vec<Message>,
+        n: usize,
+    ) -> Result<Vec<MessageContent>, Error> {
+        self.select(&messages, None)
+            .generate_images(messages, n)
+            .await
+    }
+}
+
+impl RouterLM {
+    fn select(&self, messages: &[Message], schema: Option<&Schema>) -> &Arc<dyn LM> {
+        let route = self
+            .routes
+            .iter()
+            .position(|(predicate, _)| predicate(messages, schema));
+
+        match route {
+            Some(i) => {
+                debug!("Routing call to route {i}");
+                &self.routes[i].1
+            }
+            None => &self.default,
+        }
+    }
+}
@@ -0,0 +1,60 @@
+use std::cell::RefCell;
+
+use serde::ser::SerializeMap;
+use serde::{Serialize, Serializer};
+use serde_json::Value;
+
+use crate::Error;
+use crate::lm::MessageContent;
+
+thread_local! {
+    static MEDIA: RefCell<Option<Vec<MessageContent>>> = const { RefCell::new(None) };
+}
+
+/// Key of the placeholder objects media serialize to in [`serialize`].
+const PLACEHOLDER: &str = "$dars_media";
+
+/// Serialize an adapter input, replacing media (e.g. [`Image`](crate::Image))
+/// anywhere in the value with `{"$dars_media": i}` placeholders referring to
+/// the `i`-th returned part.
+pub(crate) fn serialize<T: Serialize>(value: &T) -> Result<(Value, Vec<MessageContent>), Error> {
+    let outer = MEDIA.replace(Some(vec![]));
+    let value = serde_json::to_value(value);
+    let media = MEDIA.replace(outer).unwrap_or_default();
+    Ok((value?, media))
+}
+
+/// Serialize media as a placeholder inside [`serialize`], otherwise with `serialize`.
+pub(crate) fn serialize_media<S: Serializer>(
+    serializer: S,
+    content: impl FnOnce() -> MessageContent,
+    serialize: impl FnOnce(S) -> Result<S::Ok, S::Error>,
+) -> Result<S::Ok, S::Error> {
+    let index = MEDIA.with_borrow_mut(|media| {
+        let media = media.as_mut()?;
+        media.push(content());
+        Some(media.len() - 1)
+    });
+
+    match index {
+        Some(index) => {
+            let mut map = serializer.serialize_map(Some(1))?;
+            map.serialize_entry(PLACEHOLDER, &index)?;
+            map.end()
+        }
+        None => serialize(serializer),
+    }
+}
+
+/// Placeholder referring to the `index`-th media part.
+pub(crate) fn placeholder_value(index: usize) -> Value {
+    serde_json::json!({ PLACEHOLDER: index })
+}
+
+/// Returns the media index if `value` is a placeholder.
+pub(crate) fn placeholder(value: &Value) -> Option<usize> {
+    match value {
+        Value::Object(kv) if kv.len() == 1 => kv.get(PLACEHOLDER)?.as_u64().map(|i| i as usize),
+        _ => None,
+    }
+}
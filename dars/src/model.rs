@@ -2,6 +2,7 @@ use std::fmt::Debug;
 
 use schemars::JsonSchema;
 use serde::{Serialize, de::DeserializeOwned};
+use serde_json::{Map, Value};
 
 use crate::Field;
 
@@ -9,6 +10,14 @@ pub trait Model
 where
     Self: Sized + Debug + Serialize + DeserializeOwned + JsonSchema + Send + Sync + 'static,
 {
-    /// Returns fields in ths model. The returned tuple is `(name, description)`.
+    /// Returns the fields of the model, with their JSON names and descriptions.
     fn fields() -> &'static [Field];
 }
+
+/// JSON object model of [`DynamicSignature`](crate::DynamicSignature) inputs and
+/// outputs. The fields are only known at runtime, from the signature.
+impl Model for Map<String, Value> {
+    fn fields() -> &'static [Field] {
+        &[]
+    }
+}
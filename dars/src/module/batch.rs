@@ -0,0 +1,100 @@
+use std::sync::Arc;
+
+use futures::{StreamExt, stream};
+
+use super::Module;
+use crate::Error;
+
+/// Default number of inputs processed concurrently by [`Module::batch`].
+const DEFAULT_CONCURRENCY: usize = 8;
+
+/// Progress of a [`Module::batch`] call, reported after each finished input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progress {
+    /// Number of finished inputs, including failed ones.
+    pub completed: usize,
+    /// Number of failed inputs.
+    pub failed: usize,
+    /// Total number of inputs.
+    pub total: usize,
+}
+
+/// Configuration of a [`Module::batch`] call.
+#[derive(Clone)]
+pub struct BatchConfig {
+    /// Maximum number of inputs processed concurrently.
+    pub concurrency: usize,
+    /// Stop at the first error. Inputs that were not finished by then fail
+    /// with [`Error::Cancelled`].
+    pub fail_fast: bool,
+    /// Callback invoked after each finished input.
+    pub on_progress: Option<Arc<dyn Fn(Progress) + Send + Sync>>,
+}
+
+impl Default for BatchConfig {
+    fn default() -> Self {
+        Self {
+            concurrency: DEFAULT_CONCURRENCY,
+            fail_fast: false,
+            on_progress: None,
+        }
+    }
+}
+
+impl BatchConfig {
+    pub fn concurrency(concurrency: usize) -> Self {
+        Self {
+            concurrency,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_fail_fast(mut self, fail_fast: bool) -> Self {
+        self.fail_fast = fail_fast;
+        self
+    }
+
+    pub fn with_progress(mut self, on_progress: impl Fn(Progress) + Send + Sync + 'static) -> Self {
+        self.on_progress = Some(Arc::new(on_progress));
+        self
+    }
+}
+
+pub(crate) async fn run<M: Module + ?Sized>(
+    module: &M,
+    inputs: Vec<M::Input>,
+    config: BatchConfig,
+) -> Vec<Result<M::Output, Error>> {
+    let total = inputs.len();
+    let mut results = (0..total).map(|_| None).collect::<Vec<_>>();
+    let mut progress = Progress {
+        completed: 0,
+        failed: 0,
+        total,
+    };
+
+    let mut calls = stream::iter(inputs.into_iter().enumerate())
+        .map(|(i, input)| async move { (i, module.call(input).await) })
+        .buffer_unordered(config.concurrency.max(1));
+
+    while let Some((i, result)) = calls.next().await {
+        let failed = result.is_err();
+        progress.completed += 1;
+        if failed {
+            progress.failed += 1;
+        }
+        if let Some(on_progress) = &config.on_progress {
+            on_progress(progress);
+        }
+
+        results[i] = Some(result);
+        if failed && config.fail_fast {
+            break;
+        }
+    }
+
+    results
+        .into_iter()
+        .map(|r| r.unwrap_or(Err(Error::Cancelled)))
+        .collect()
+}
@@ -0,0 +1,120 @@
+use async_trait::async_trait;
+use serde::Serialize;
+use tracing::warn;
+
+use super::Module;
+use crate::{CallContext, Error, callback};
+
+/// Temperature used for all but the first attempt.
+pub(crate) const ROLLOUT_TEMPERATURE: f32 = 1.0;
+
+/// Reward function scoring a module output for the given input.
+pub type Reward<M> =
+    Box<dyn Fn(&<M as Module>::Input, &<M as Module>::Output) -> f64 + Send + Sync>;
+
+/// Runs a module up to `n` times and returns the output with the highest reward.
+///
+/// The first attempt uses the module's own settings, the following attempts
+/// sample with a higher temperature and a distinct rollout id.
+pub struct BestOfN<M: Module> {
+    module: M,
+    n: usize,
+    reward: Reward<M>,
+    threshold: Option<f64>,
+    fail_count: Option<usize>,
+}
+
+impl<M: Module> BestOfN<M> {
+    pub fn new(
+        module: M,
+        n: usize,
+        reward: impl Fn(&M::Input, &M::Output) -> f64 + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            module,
+            n,
+            reward: Box::new(reward),
+            threshold: None,
+            fail_count: None,
+        }
+    }
+
+    /// Stop as soon as an output reaches `threshold`.
+    pub fn with_threshold(mut self, threshold: f64) -> Self {
+        self.threshold = Some(threshold);
+        self
+    }
+
+    /// Fail after `fail_count` attempts returned an error. Defaults to `n`.
+    pub fn with_fail_count(mut self, fail_count: usize) -> Self {
+        self.fail_count = Some(fail_count);
+        self
+    }
+}
+
+#[async_trait]
+impl<M: Module> Module for BestOfN<M>
+where
+    M::Input: Clone + Serialize + Send + Sync,
+    M::Output: Serialize + Send,
+{
+    type Input = M::Input;
+    type Output = M::Output;
+
+    async fn call(&self, input: Self::Input) -> Result<Self::Output, Error> {
+        callback::traced("BestOfN", input, |input| self.forward(input)).await
+    }
+}
+
+impl<M: Module> BestOfN<M>
+where
+    M::Input: Clone + Send + Sync,
+    M::Output: Send,
+{
+    async fn forward(&self, input: M::Input) -> Result<M::Output, Error> {
+        let fail_count = self.fail_count.unwrap_or(self.n);
+
+        let mut best: Option<(f64, M::Output)> = None;
+        let mut last_err = None;
+        let mut failures = 0;
+        for i in 0..self.n {
+            let ctx = rollout_context(CallContext::current(), i);
+            match ctx.scope(self.module.call(input.clone())).await {
+                Ok(output) => {
+                    let reward = (self.reward)(&input, &output);
+                    let done = self.threshold.is_some_and(|t| reward >= t);
+                    if best.as_ref().is_none_or(|(r, _)| reward > *r) {
+                        best = Some((reward, output));
+                    }
+                    if done {
+                        break;
+                    }
+                }
+                Err(e) => {
+                    warn!("BestOfN attempt {i} failed: {e}");
+                    failures += 1;
+                    if failures >= fail_count {
+                        return Err(e);
+                    }
+                    last_err = Some(e);
+                }
+            }
+        }
+
+        match (best, last_err) {
+            (Some((_, output)), _) => Ok(output),
+            (None, Some(e)) => Err(e),
+            (None, None) => Err(Error::InvalidArgument("BestOfN with n = 0".into())),
+        }
+    }
+}
+
+/// Context for the `i`-th attempt of a module.
+pub(crate) fn rollout_context(ctx: CallContext, i: usize) -> CallContext {
+    let ctx = ctx.with_rollout_id(i);
+    if i == 0 {
+        ctx
+    } else {
+        ctx.with_temperature(ROLLOUT_TEMPERATURE)
+    }
+}
@@ -0,0 +1,43 @@
+/// Predicate over a module output with the message fed back to the LM when it fails.
+pub(crate) struct Check<O> {
+    pub kind: CheckKind,
+    pub message: String,
+    predicate: Box<dyn Fn(&O) -> bool + Send + Sync>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum CheckKind {
+    /// Raises [`Error::Assertion`](crate::Error::Assertion) when retries are exhausted.
+    Assert,
+    /// Only logs a warning when retries are exhausted.
+    Suggest,
+}
+
+impl<O> Check<O> {
+    pub fn new(
+        kind: CheckKind,
+        predicate: impl Fn(&O) -> bool + Send + Sync + 'static,
+        message: impl Into<String>,
+    ) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+            predicate: Box::new(predicate),
+        }
+    }
+
+    pub fn passes(&self, output: &O) -> bool {
+        (self.predicate)(output)
+    }
+}
+
+/// Format the feedback message for the LM after a failed attempt.
+pub(crate) fn feedback<'a>(failures: impl IntoIterator<Item = &'a str>) -> String {
+    let mut buf =
+        String::from("Your previous output did not satisfy the following requirements:\n");
+    for failure in failures {
+        buf += &format!("- {failure}\n");
+    }
+    buf += "\nFix your output and respond again in the same format.";
+    buf
+}
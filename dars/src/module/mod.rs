@@ -1,7 +1,14 @@
 use async_trait::async_trait;
 
+mod batch;
+mod best_of_n;
+mod check;
 mod predict;
+mod refine;
+pub use batch::{BatchConfig, Progress};
+pub use best_of_n::{BestOfN, Reward};
 pub use predict::Predict;
+pub use refine::{Feedback, Refine};
 
 use crate::Error;
 
@@ -11,4 +18,18 @@ pub trait Module: Send + Sync + 'static {
     type Output;
 
     async fn call(&self, input: Self::Input) -> Result<Self::Output, Error>;
+
+    /// Call the module on many inputs concurrently. Errors are captured per input
+    /// and the results are in the order of `inputs`.
+    async fn batch(
+        &self,
+        inputs: Vec<Self::Input>,
+        config: BatchConfig,
+    ) -> Vec<Result<Self::Output, Error>>
+    where
+        Self::Input: Send,
+        Self::Output: Send,
+    {
+        batch::run(self, inputs, config).await
+    }
 }
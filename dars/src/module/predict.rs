@@ -1,27 +1,87 @@
 use std::sync::Arc;
 
 use async_trait::async_trait;
+use regex::Regex;
+use schemars::Schema;
+use tracing::warn;
 
 use super::Module;
+use super::check::{Check, CheckKind, feedback};
 use crate::adapter::{Adapter, json::JsonAdapter};
-use crate::{Error, Signature, lm::LM};
+use crate::lm::{Message, MessageContent};
+use crate::{CallContext, Error, Signature, lm::LM};
+use crate::{callback, document};
 
+/// Default number of retries of a [`Predict`] with assertions or suggestions.
+const DEFAULT_MAX_RETRIES: usize = 2;
+
+/// Module calling the LM once per input, formatted and parsed by the adapter.
+///
+/// Invalid outputs are only retried with [`Predict::with_max_retries`], or by
+/// default when assertions or suggestions are added, so a plain `Predict`
+/// makes a single LM call.
 pub struct Predict<S: Signature> {
+    name: String,
     lm: Arc<dyn LM>,
     adapter: JsonAdapter<S>,
+    checks: Vec<Check<S::Output>>,
+    max_retries: Option<usize>,
 }
 
 impl<S: Signature> Predict<S> {
     pub fn new(lm: Arc<dyn LM>, signature: S) -> Self {
         Self {
+            name: format!("Predict<{}>", short_type_name::<S>()),
             lm,
             adapter: JsonAdapter::new(signature),
+            checks: vec![],
+            max_retries: None,
         }
     }
 
     pub fn set_lm(&mut self, lm: Arc<dyn LM>) {
         self.lm = lm;
     }
+
+    /// Require the output to satisfy `predicate`. If it does not, the LM is called
+    /// again with its previous output and `message`; once retries are exhausted
+    /// the call fails with [`Error::Assertion`].
+    pub fn assert(
+        mut self,
+        predicate: impl Fn(&S::Output) -> bool + Send + Sync + 'static,
+        message: impl Into<String>,
+    ) -> Self {
+        self.checks
+            .push(Check::new(CheckKind::Assert, predicate, message));
+        self
+    }
+
+    /// Like [`Predict::assert`], but once retries are exhausted the last output
+    /// is returned and the failure is only logged.
+    pub fn suggest(
+        mut self,
+        predicate: impl Fn(&S::Output) -> bool + Send + Sync + 'static,
+        message: impl Into<String>,
+    ) -> Self {
+        self.checks
+            .push(Check::new(CheckKind::Suggest, predicate, message));
+        self
+    }
+
+    /// Set the number of retries after a failed check, output validation or
+    /// missing output field. Defaults to 2 with checks and 0 without.
+    pub fn with_max_retries(mut self, max_retries: usize) -> Self {
+        self.max_retries = Some(max_retries);
+        self
+    }
+
+    fn max_retries(&self) -> usize {
+        self.max_retries.unwrap_or(if self.checks.is_empty() {
+            0
+        } else {
+            DEFAULT_MAX_RETRIES
+        })
+    }
 }
 
 #[async_trait]
@@ -30,13 +90,126 @@ impl<S: Signature> Module for Predict<S> {
     type Output = <S as Signature>::Output;
 
     async fn call(&self, input: Self::Input) -> Result<Self::Output, Error> {
+        callback::traced(&self.name, input, |input| self.forward(input)).await
+    }
+}
+
+impl<S: Signature> Predict<S> {
+    async fn forward(&self, input: S::Input) -> Result<S::Output, Error> {
         // Format input
-        let (messages, schema) = self.adapter.format(input)?;
+        let (mut messages, schema) = self.adapter.format(input)?;
+        callback::adapter_format(&messages, schema.as_ref());
+
+        // Add feedback on previous attempts of an enclosing module (e.g. `Refine`)
+        if let Some(feedback) = CallContext::current().feedback {
+            messages.push(Message::User {
+                content: vec![MessageContent::Text { text: feedback }],
+            });
+        }
+
+        // Generate the image outputs once, the text outputs are retried below
+        let images = match self.adapter.format_images(&messages) {
+            Some((messages, n)) => callback::lm_images(self.lm.as_ref(), messages, n).await?,
+            None => vec![],
+        };
+
+        let max_retries = self.max_retries();
+        let mut retry: Option<(String, String)> = None;
+        for attempt in 0..=max_retries {
+            let last_attempt = attempt == max_retries;
+
+            // Add the previous output and the feedback on retries
+            let mut request = messages.clone();
+            if let Some((output, feedback)) = retry.take() {
+                request.push(Message::Assistant {
+                    content: MessageContent::Text { text: output },
+                });
+                request.push(Message::User {
+                    content: vec![MessageContent::Text { text: feedback }],
+                });
+            }
+
+            // Call LM with the json schema for the output
+            let resp = if self.adapter.has_text_outputs() {
+                self.call_lm(&mut messages, request, schema.clone()).await?
+            } else {
+                String::new()
+            };
+
+            // Parse output
+            let output = self.adapter.parse_with_images(resp.clone(), images.clone());
+            callback::adapter_parse(&resp, &output);
+            let output = match output {
+                Ok(output) => output,
+                Err(Error::Validation(violations)) if !last_attempt => {
+                    let failures = violations.iter().map(|v| v.to_string()).collect::<Vec<_>>();
+                    retry = Some((resp, feedback(failures.iter().map(String::as_str))));
+                    continue;
+                }
+                Err(Error::MissingFields(fields)) if !last_attempt => {
+                    let failures = fields
+                        .iter()
+                        .map(|f| format!("missing required output field `{f}`"))
+                        .collect::<Vec<_>>();
+                    retry = Some((resp, feedback(failures.iter().map(String::as_str))));
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+
+            // Check assertions and suggestions
+            let failed = self
+                .checks
+                .iter()
+                .filter(|c| !c.passes(&output))
+                .collect::<Vec<_>>();
+            if failed.is_empty() {
+                return Ok(output);
+            }
 
-        // Call LM with the json schema for the output
-        let resp = self.lm.call(messages, schema).await?;
+            if !last_attempt {
+                retry = Some((resp, feedback(failed.iter().map(|c| c.message.as_str()))));
+                continue;
+            }
 
-        // Parse output
-        self.adapter.parse(resp)
+            if let Some(assert) = failed.iter().find(|c| c.kind == CheckKind::Assert) {
+                return Err(Error::Assertion(assert.message.clone()));
+            }
+            for suggestion in failed {
+                warn!("Suggestion failed: {}", suggestion.message);
+            }
+            return Ok(output);
+        }
+
+        unreachable!("the last attempt always returns")
     }
+
+    /// Call the LM with `request`, the `messages` followed by the feedback of a
+    /// retry. LMs not accepting file parts get the text of the documents instead,
+    /// which also replaces the files in `messages` for the next retries.
+    async fn call_lm(
+        &self,
+        messages: &mut [Message],
+        mut request: Vec<Message>,
+        schema: Option<Schema>,
+    ) -> Result<String, Error> {
+        match callback::lm_call(self.lm.as_ref(), request.clone(), schema.clone()).await {
+            Err(Error::UnsupportedContent(reason)) if document::has_files(&request) => {
+                warn!("LM does not accept file parts, sending the text of the documents: {reason}");
+                document::files_to_text(&mut request)?;
+                messages.clone_from_slice(&request[..messages.len()]);
+                callback::lm_call(self.lm.as_ref(), request, schema).await
+            }
+            result => result,
+        }
+    }
+}
+
+/// Type name without module paths, e.g. `Vec<String>` for `alloc::vec::Vec<alloc::string::String>`.
+fn short_type_name<T>() -> String {
+    let name = std::any::type_name::<T>();
+    Regex::new(r"\w+::")
+        .unwrap()
+        .replace_all(name, "")
+        .into_owned()
 }
@@ -0,0 +1,127 @@
+use async_trait::async_trait;
+use serde::Serialize;
+use tracing::warn;
+
+use super::Module;
+use super::best_of_n::{Reward, rollout_context};
+use crate::{CallContext, Error, callback};
+
+/// Feedback function describing why an output did not reach the threshold.
+pub type Feedback<M> =
+    Box<dyn Fn(&<M as Module>::Input, &<M as Module>::Output, f64) -> String + Send + Sync>;
+
+/// Like [`BestOfN`](super::BestOfN), but each attempt that falls short of the
+/// threshold produces feedback which is added to the prompts of the next attempt.
+pub struct Refine<M: Module> {
+    module: M,
+    n: usize,
+    reward: Reward<M>,
+    threshold: f64,
+    feedback: Feedback<M>,
+    fail_count: Option<usize>,
+}
+
+impl<M: Module> Refine<M>
+where
+    M::Output: std::fmt::Debug,
+{
+    pub fn new(
+        module: M,
+        n: usize,
+        reward: impl Fn(&M::Input, &M::Output) -> f64 + Send + Sync + 'static,
+        threshold: f64,
+    ) -> Self {
+        Self {
+            module,
+            n,
+            reward: Box::new(reward),
+            threshold,
+            feedback: Box::new(move |_, output, reward| {
+                format!(
+                    "A previous attempt produced {output:?}, which scored {reward} \
+                    but at least {threshold} is required. Produce a better output."
+                )
+            }),
+            fail_count: None,
+        }
+    }
+}
+
+impl<M: Module> Refine<M> {
+    /// Set the function producing the feedback for an output below the threshold.
+    pub fn with_feedback(
+        mut self,
+        feedback: impl Fn(&M::Input, &M::Output, f64) -> String + Send + Sync + 'static,
+    ) -> Self {
+        self.feedback = Box::new(feedback);
+        self
+    }
+
+    /// Fail after `fail_count` attempts returned an error. Defaults to `n`.
+    pub fn with_fail_count(mut self, fail_count: usize) -> Self {
+        self.fail_count = Some(fail_count);
+        self
+    }
+}
+
+#[async_trait]
+impl<M: Module> Module for Refine<M>
+where
+    M::Input: Clone + Serialize + Send + Sync,
+    M::Output: Serialize + Send,
+{
+    type Input = M::Input;
+    type Output = M::Output;
+
+    async fn call(&self, input: Self::Input) -> Result<Self::Output, Error> {
+        callback::traced("Refine", input, |input| self.forward(input)).await
+    }
+}
+
+impl<M: Module> Refine<M>
+where
+    M::Input: Clone + Send + Sync,
+    M::Output: Send,
+{
+    async fn forward(&self, input: M::Input) -> Result<M::Output, Error> {
+        let fail_count = self.fail_count.unwrap_or(self.n);
+
+        let mut best: Option<(f64, M::Output)> = None;
+        let mut feedback: Option<String> = None;
+        let mut last_err = None;
+        let mut failures = 0;
+        for i in 0..self.n {
+            let mut ctx = rollout_context(CallContext::current(), i);
+            if let Some(feedback) = feedback.take() {
+                ctx = ctx.with_feedback(feedback);
+            }
+
+            match ctx.scope(self.module.call(input.clone())).await {
+                Ok(output) => {
+                    let reward = (self.reward)(&input, &output);
+                    if reward >= self.threshold {
+                        return Ok(output);
+                    }
+                    feedback = Some((self.feedback)(&input, &output, reward));
+                    if best.as_ref().is_none_or(|(r, _)| reward > *r) {
+                        best = Some((reward, output));
+                    }
+                }
+                Err(e) => {
+                    warn!("Refine attempt {i} failed: {e}");
+                    failures += 1;
+                    if failures >= fail_count {
+                        return Err(e);
+                    }
+                    last_err = Some(e);
+                }
+            }
+        }
+
+        match (best, last_err) {
+            (Some((_, output)), _) => Ok(output),
+            (None, Some(e)) => Err(e),
+            (None, None) => Err(Error::InvalidArgument("Refine with n = 0".into())),
+        }
+    }
+}
@@ -0,0 +1,248 @@
+//! OpenTelemetry spans for module and LM calls.
+//!
+//! With the `otel` feature, every traced module call (see [`traced`](crate::traced))
+//! and every LM call made by a module is wrapped in a `tracing` span whose fields
+//! follow the [GenAI semantic conventions]. Export them with a
+//! `tracing-opentelemetry` layer:
+//!
+//! ```ignore
+//! tracing_subscriber::registry()
+//!     .with(tracing_opentelemetry::layer().with_tracer(tracer))
+//!     .init();
+//! ```
+//!
+//! LM spans are named `chat {model}` and carry the request parameters, the
+//! response id, model and finish reasons, and the token usage. Prompts and
+//! completions may contain sensitive data, so they're only recorded (as
+//! `gen_ai.input.messages` and `gen_ai.output.messages`) after opting in with
+//! [`set_capture_content`] or the `OTEL_INSTRUMENTATION_GENAI_CAPTURE_MESSAGE_CONTENT`
+//! environment variable.
+//!
+//! Spans are emitted where modules dispatch calls, not by the [`LM`](crate::lm::LM)
+//! and [`Module`](crate::Module) traits themselves. Calling [`LM::call`](crate::lm::LM::call)
+//! directly, or a module not wrapped in [`traced`](crate::traced), emits no span.
+//! Wrapper LMs such as [`FallbackLM`](crate::lm::FallbackLM) and
+//! [`RouterLM`](crate::lm::RouterLM) get a single span for the call, without
+//! spans for the attempts of their inner backends.
+//!
+//! [GenAI semantic conventions]: https://opentelemetry.io/docs/specs/semconv/gen-ai/
+
+pub(crate) use imp::*;
+
+#[cfg(feature = "otel")]
+pub use imp::{capture_content, set_capture_content};
+
+#[cfg(feature = "otel")]
+mod imp {
+    use std::sync::OnceLock;
+    use std::sync::atomic::{AtomicU8, Ordering};
+
+    use serde_json::{Value, json};
+    use tracing::field::Empty;
+    use tracing::{Span, info_span};
+
+    use crate::lm::{ErrorClass, Message, MessageContent};
+    use crate::{Error, audio};
+
+    /// Environment variable enabling content capture.
+    const CAPTURE_CONTENT_ENV: &str = "OTEL_INSTRUMENTATION_GENAI_CAPTURE_MESSAGE_CONTENT";
+
+    /// Content capture set with [`set_capture_content`]: 0 = unset, 1 = off, 2 = on.
+    static CAPTURE_CONTENT: AtomicU8 = AtomicU8::new(0);
+
+    /// Record prompts and completions on LM spans.
+    pub fn set_capture_content(enabled: bool) {
+        CAPTURE_CONTENT.store(if enabled { 2 } else { 1 }, Ordering::Relaxed);
+    }
+
+    /// Returns whether prompts and completions are recorded on LM spans.
+    pub fn capture_content() -> bool {
+        static FROM_ENV: OnceLock<bool> = OnceLock::new();
+        match CAPTURE_CONTENT.load(Ordering::Relaxed) {
+            0 => *FROM_ENV.get_or_init(|| {
+                std::env::var(CAPTURE_CONTENT_ENV).is_ok_and(|v| v.eq_ignore_ascii_case("true"))
+            }),
+            v => v == 2,
+        }
+    }
+
+    pub(crate) fn module_span(name: &str) -> Span {
+        info_span!(
+            "dars.module",
+            otel.name = name,
+            otel.kind = "internal",
+            otel.status_code = Empty,
+            dars.module.name = name,
+            error.type = Empty,
+        )
+    }
+
+    pub(crate) fn lm_span(messages: &[Message]) -> Span {
+        let span = info_span!(
+            "gen_ai.chat",
+            otel.name = "chat",
+            otel.kind = "client",
+            otel.status_code = Empty,
+            gen_ai.operation.name = "chat",
+            gen_ai.provider.name = Empty,
+            gen_ai.request.model = Empty,
+            gen_ai.request.temperature = Empty,
+            gen_ai.request.max_tokens = Empty,
+            gen_ai.request.top_p = Empty,
+            gen_ai.response.id = Empty,
+            gen_ai.response.model = Empty,
+            gen_ai.response.finish_reasons = Empty,
+            gen_ai.usage.input_tokens = Empty,
+            gen_ai.usage.output_tokens = Empty,
+            gen_ai.input.messages = Empty,
+            gen_ai.output.messages = Empty,
+            error.type = Empty,
+        );
+        if capture_content() {
+            let messages = messages.iter().map(message_value).collect::<Vec<_>>();
+            span.record("gen_ai.input.messages", Value::from(messages).to_string());
+        }
+        span
+    }
+
+    pub(crate) fn record_module_result<O>(span: &Span, result: &Result<O, Error>) {
+        if let Err(e) = result {
+            record_error(span, e);
+        }
+    }
+
+    pub(crate) fn record_lm_result(span: &Span, result: Result<&str, &Error>) {
+        match result {
+            Ok(completion) if capture_content() => {
+                let message = json!([{
+                    "role": "assistant",
+                    "parts": [{"type": "text", "content": completion}],
+                }]);
+                span.record("gen_ai.output.messages", message.to_string());
+            }
+            Ok(_) => {}
+            Err(e) => record_error(span, e),
+        }
+    }
+
+    /// Request attributes recorded by LM implementations on the current LM span.
+    #[cfg_attr(not(feature = "openai"), allow(dead_code))]
+    #[derive(Debug, Default)]
+    pub(crate) struct Request<'a> {
+        pub provider: &'a str,
+        pub model: &'a str,
+        pub temperature: Option<f32>,
+        pub max_tokens: Option<u32>,
+        pub top_p: Option<f32>,
+    }
+
+    /// Response attributes recorded by LM implementations on the current LM span.
+    #[cfg_attr(not(feature = "openai"), allow(dead_code))]
+    #[derive(Debug, Default)]
+    pub(crate) struct Response<'a> {
+        pub id: &'a str,
+        pub model: &'a str,
+        pub finish_reasons: Vec<String>,
+        pub input_tokens: Option<u32>,
+        pub output_tokens: Option<u32>,
+    }
+
+    #[cfg_attr(not(feature = "openai"), allow(dead_code))]
+    pub(crate) fn record_request(request: Request) {
+        let span = Span::current();
+        span.record("otel.name", format!("chat {}", request.model));
+        span.record("gen_ai.provider.name", request.provider);
+        span.record("gen_ai.request.model", request.model);
+        if let Some(temperature) = request.temperature {
+            span.record("gen_ai.request.temperature", temperature as f64);
+        }
+        if let Some(max_tokens) = request.max_tokens {
+            span.record("gen_ai.request.max_tokens", max_tokens);
+        }
+        if let Some(top_p) = request.top_p {
+            span.record("gen_ai.request.top_p", top_p as f64);
+        }
+    }
+
+    #[cfg_attr(not(feature = "openai"), allow(dead_code))]
+    pub(crate) fn record_response(response: Response) {
+        let span = Span::current();
+        span.record("gen_ai.response.id", response.id);
+        span.record("gen_ai.response.model", response.model);
+        span.record(
+            "gen_ai.response.finish_reasons",
+            Value::from(response.finish_reasons).to_string(),
+        );
+        if let Some(input_tokens) = response.input_tokens {
+            span.record("gen_ai.usage.input_tokens", input_tokens);
+        }
+        if let Some(output_tokens) = response.output_tokens {
+            span.record("gen_ai.usage.output_tokens", output_tokens);
+        }
+    }
+
+    fn record_error(span: &Span, error: &Error) {
+        let error_type = match ErrorClass::of(error) {
+            ErrorClass::RateLimit => "rate_limit",
+            ErrorClass::Unavailable => "unavailable",
+            ErrorClass::InvalidRequest => "invalid_request",
+            ErrorClass::InvalidResponse => "invalid_response",
+            ErrorClass::Other => "_OTHER",
+        };
+        span.record("otel.status_code", "ERROR");
+        span.record("error.type", error_type);
+    }
+
+    /// Message in the GenAI semantic conventions format.
+    fn message_value(message: &Message) -> Value {
+        let part = |c: &MessageContent| match c {
+            MessageContent::Text { text } => json!({"type": "text", "content": text}),
+            MessageContent::Image { url } => {
+                json!({"type": "uri", "modality": "image", "uri": url})
+            }
+            MessageContent::File {
+                data, mime_type, ..
+            } => {
+                json!({"type": "blob", "modality": "document", "mime_type": mime_type, "content": data})
+            }
+            MessageContent::Audio { data, format } => {
+                json!({"type": "blob", "modality": "audio", "mime_type": audio::mime_type(format), "content": data})
+            }
+        };
+
+        match message {
+            Message::System { instruction } => json!({
+                "role": "system",
+                "parts": [{"type": "text", "content": instruction}],
+            }),
+            Message::User { content } => json!({
+                "role": "user",
+                "parts": content.iter().map(part).collect::<Vec<_>>(),
+            }),
+            Message::Assistant { content } => json!({
+                "role": "assistant",
+                "parts": [part(content)],
+            }),
+        }
+    }
+}
+
+#[cfg(not(feature = "otel"))]
+mod imp {
+    use tracing::Span;
+
+    use crate::Error;
+    use crate::lm::Message;
+
+    pub(crate) fn module_span(_name: &str) -> Span {
+        Span::none()
+    }
+
+    pub(crate) fn lm_span(_messages: &[Message]) -> Span {
+        Span::none()
+    }
+
+    pub(crate) fn record_module_result<O>(_span: &Span, _result: &Result<O, Error>) {}
+
+    pub(crate) fn record_lm_result(_span: &Span, _result: Result<&str, &Error>) {}
+}
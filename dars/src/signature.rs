@@ -1,5 +1,8 @@
 use std::fmt::Debug;
 
+use schemars::{JsonSchema, Schema};
+use serde_json::Value;
+
 use crate::{Field, model::Model};
 
 pub trait Signature
@@ -20,4 +23,74 @@ where
 
     /// Returns the [`Schema`](schemars::Schema) for a field by name.
     fn field(&self, name: &str) -> Option<&schemars::Schema>;
+
+    /// Returns the [`Schema`] of the JSON object produced for the output.
+    fn output_schema(&self) -> Schema {
+        schemars::schema_for!(Self::Output)
+    }
+}
+
+/// Panics if a field name is declared by both models. Called when creating
+/// signatures on existing models, `#[Signature(input = .., output = ..)]`.
+pub fn check_distinct_fields(input: &[Field], output: &[Field]) {
+    if let Some(field) = input
+        .iter()
+        .find(|i| output.iter().any(|o| o.name == i.name))
+    {
+        panic!(
+            "Field `{}` is declared by both the input and output models",
+            field.name
+        );
+    }
+}
+
+/// Schemas of the fields of a model, taken from the properties of its schema.
+/// Each field schema keeps the definitions of the model, so references resolve.
+/// A field referencing a definition is replaced by the definition, so markers of
+/// the field type, e.g. on [`Image`](crate::Image), are found on the field schema.
+pub fn field_schemas<M: JsonSchema>() -> Vec<(String, Schema)> {
+    let schema = schemars::schema_for!(M).to_value();
+    let Some(Value::Object(properties)) = schema.get("properties") else {
+        return vec![];
+    };
+    let defs = schema.get("$defs");
+    properties
+        .iter()
+        .map(|(name, property)| {
+            let mut property = resolve_ref(property, defs);
+            if let (Some(defs), Value::Object(property)) = (defs, &mut property) {
+                property.insert("$defs".to_string(), defs.clone());
+            }
+            let schema = Schema::try_from(property).unwrap_or_else(|_| Schema::from(true));
+            (name.clone(), schema)
+        })
+        .collect()
+}
+
+/// Replaces a `{"$ref": "#/$defs/.."}` schema by the definition, titled like
+/// the root schema of the type and keeping the other keywords of the schema,
+/// e.g. its description.
+fn resolve_ref(property: &Value, defs: Option<&Value>) -> Value {
+    let Some((name, def)) = property
+        .get("$ref")
+        .and_then(Value::as_str)
+        .and_then(|r| r.strip_prefix("#/$defs/"))
+        .and_then(|name| Some((name, defs?.get(name)?)))
+    else {
+        return property.clone();
+    };
+    let (Value::Object(def), Value::Object(property)) = (def, property) else {
+        return property.clone();
+    };
+    let mut resolved = def.clone();
+    resolved
+        .entry("title")
+        .or_insert_with(|| Value::String(name.to_string()));
+    resolved.extend(
+        property
+            .iter()
+            .filter(|(key, _)| *key != "$ref")
+            .map(|(key, value)| (key.clone(), value.clone())),
+    );
+    Value::Object(resolved)
 }
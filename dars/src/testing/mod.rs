@@ -0,0 +1,11 @@
+//! LMs for deterministic offline tests.
+//!
+//! - [`ScriptedLM`] returns a queue of canned responses.
+//! - [`RecordingLM`] wraps a real LM and records its calls into a fixture file.
+//! - [`ReplayLM`] serves the responses of a fixture file by matching requests.
+
+mod replay;
+mod scripted;
+
+pub use replay::{MatchMode, Record, RecordingLM, ReplayLM};
+pub use scripted::ScriptedLM;
@@ -0,0 +1,240 @@
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use schemars::Schema;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::Error;
+use crate::lm::{LM, Message, MessageContent};
+
+/// A recorded LM call.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Record {
+    pub messages: Vec<Message>,
+    pub schema: Option<Schema>,
+    pub response: String,
+    /// Images returned by [`LM::generate_images`], `None` for text calls.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub images: Option<Vec<MessageContent>>,
+}
+
+/// LM forwarding calls to another LM and recording them into a fixture file
+/// that can be served by [`ReplayLM`].
+///
+/// The fixture is written by [`RecordingLM::save`], and when the LM is dropped
+/// after recording calls.
+pub struct RecordingLM {
+    lm: Arc<dyn LM>,
+    path: PathBuf,
+    records: Mutex<Vec<Record>>,
+}
+
+impl RecordingLM {
+    /// Record the calls of `lm` into the fixture at `path`, replacing its content.
+    pub fn new(lm: Arc<dyn LM>, path: impl Into<PathBuf>) -> Self {
+        Self {
+            lm,
+            path: path.into(),
+            records: Mutex::new(vec![]),
+        }
+    }
+
+    /// Returns the calls recorded so far.
+    pub fn records(&self) -> Vec<Record> {
+        self.records.lock().unwrap().clone()
+    }
+
+    /// Write the calls recorded so far to the fixture, replacing its content.
+    pub fn save(&self) -> Result<(), Error> {
+        let fixture = serde_json::to_string_pretty(&self.records())?;
+        std::fs::write(&self.path, fixture)?;
+        Ok(())
+    }
+}
+
+impl Drop for RecordingLM {
+    fn drop(&mut self) {
+        // Keep an existing fixture when nothing was recorded, e.g. after an early panic
+        let records = self.records.get_mut();
+        if records.map_or_else(|e| e.into_inner().is_empty(), |r| r.is_empty()) {
+            return;
+        }
+        if let Err(e) = self.save() {
+            warn!(
+                "Failed to save the recording to {}: {e}",
+                self.path.display()
+            );
+        }
+    }
+}
+
+#[async_trait]
+impl LM for RecordingLM {
+    async fn call(&self, messages: Vec<Message>, schema: Option<Schema>) -> Result<String, Error> {
+        let response = self.lm.call(messages.clone(), schema.clone()).await?;
+        self.records.lock().unwrap().push(Record {
+            messages,
+            schema,
+            response: response.clone(),
+            images: None,
+        });
+        Ok(response)
+    }
+
+    async fn generate_images(
+        &self,
+        messages: Vec<Message>,
+        n: usize,
+    ) -> Result<Vec<MessageContent>, Error> {
+        let images = self.lm.generate_images(messages.clone(), n).await?;
+        self.records.lock().unwrap().push(Record {
+            messages,
+            schema: None,
+            response: String::new(),
+            images: Some(images.clone()),
+        });
+        Ok(images)
+    }
+}
+
+/// How [`ReplayLM`] matches calls to records.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MatchMode {
+    /// Messages and schema must be equal.
+    Exact,
+    /// Schema must be equal and the words of the messages must have a Jaccard
+    /// similarity of at least `threshold` (0 to 1). The most similar record wins.
+    Fuzzy { threshold: f64 },
+}
+
+/// LM serving recorded responses for matching calls.
+///
+/// Records matching a call are served in the recorded order, so a program
+/// making the same call several times gets the responses in the original
+/// order. Once they're all used, the last one is repeated.
+pub struct ReplayLM {
+    records: Vec<Record>,
+    mode: MatchMode,
+    used: Mutex<Vec<bool>>,
+}
+
+impl ReplayLM {
+    pub fn new(records: Vec<Record>, mode: MatchMode) -> Self {
+        Self {
+            used: Mutex::new(vec![false; records.len()]),
+            records,
+            mode,
+        }
+    }
+
+    /// Load the records from a fixture written by [`RecordingLM`].
+    pub fn load(path: impl AsRef<Path>, mode: MatchMode) -> Result<Self, Error> {
+        let records = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+        Ok(Self::new(records, mode))
+    }
+
+    /// Returns the indices of the matching records, best match first. `images`
+    /// is the number of images requested, `None` for text calls.
+    fn matches(
+        &self,
+        messages: &[Message],
+        schema: Option<&Schema>,
+        images: Option<usize>,
+    ) -> Vec<usize> {
+        let candidates = self
+            .records
+            .iter()
+            .enumerate()
+            .filter(|(_, r)| r.schema.as_ref() == schema)
+            .filter(|(_, r)| r.images.as_ref().map(Vec::len) == images);
+
+        match self.mode {
+            MatchMode::Exact => candidates
+                .filter(|(_, r)| r.messages == messages)
+                .map(|(i, _)| i)
+                .collect(),
+            MatchMode::Fuzzy { threshold } => {
+                let words = words(messages);
+                let mut scored = candidates
+                    .map(|(i, r)| (i, similarity(&words, &self::words(&r.messages))))
+                    .filter(|(_, score)| *score >= threshold)
+                    .collect::<Vec<_>>();
+                // Stable sort keeps the recorded order for equal scores
+                scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+                scored.into_iter().map(|(i, _)| i).collect()
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl LM for ReplayLM {
+    async fn call(&self, messages: Vec<Message>, schema: Option<Schema>) -> Result<String, Error> {
+        let record = self.replay(&messages, schema.as_ref(), None)?;
+        Ok(record.response.clone())
+    }
+
+    async fn generate_images(
+        &self,
+        messages: Vec<Message>,
+        n: usize,
+    ) -> Result<Vec<MessageContent>, Error> {
+        let record = self.replay(&messages, None, Some(n))?;
+        Ok(record.images.clone().unwrap_or_default())
+    }
+}
+
+impl ReplayLM {
+    /// Returns the next matching record and marks it as used.
+    fn replay(
+        &self,
+        messages: &[Message],
+        schema: Option<&Schema>,
+        images: Option<usize>,
+    ) -> Result<&Record, Error> {
+        let matches = self.matches(messages, schema, images);
+
+        let mut used = self.used.lock().unwrap();
+        // Matches are sorted by similarity, reuse the last recorded one when all are used
+        let i = match matches.iter().find(|i| !used[**i]).or(matches.iter().max()) {
+            Some(i) => *i,
+            None => {
+                let request = messages
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                return Err(Error::ModelCall(format!(
+                    "ReplayLM: no recorded response matches the call ({:?}):\n{request}",
+                    self.mode
+                )));
+            }
+        };
+        used[i] = true;
+
+        Ok(&self.records[i])
+    }
+}
+
+fn words(messages: &[Message]) -> BTreeSet<String> {
+    messages
+        .iter()
+        .flat_map(|m| {
+            m.to_string()
+                .split_whitespace()
+                .map(str::to_lowercase)
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+fn similarity(a: &BTreeSet<String>, b: &BTreeSet<String>) -> f64 {
+    let union = a.union(b).count();
+    if union == 0 {
+        return 1.0;
+    }
+    a.intersection(b).count() as f64 / union as f64
+}
@@ -0,0 +1,81 @@
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+use async_trait::async_trait;
+use schemars::Schema;
+
+use crate::lm::{LM, Message, MessageContent};
+use crate::{Error, Image};
+
+/// LM returning canned responses in order and recording the calls it receives.
+pub struct ScriptedLM {
+    responses: Mutex<VecDeque<String>>,
+    images: Mutex<VecDeque<Image>>,
+    calls: Mutex<Vec<Vec<Message>>>,
+}
+
+impl ScriptedLM {
+    pub fn new(responses: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        Self {
+            responses: Mutex::new(responses.into_iter().map(Into::into).collect()),
+            images: Mutex::new(VecDeque::new()),
+            calls: Mutex::new(vec![]),
+        }
+    }
+
+    /// Script JSON responses, e.g. outputs of a signature.
+    pub fn json(responses: impl IntoIterator<Item = serde_json::Value>) -> Self {
+        Self::new(responses.into_iter().map(|r| r.to_string()))
+    }
+
+    /// Script images returned in order by [`LM::generate_images`].
+    pub fn with_images(self, images: impl IntoIterator<Item = Image>) -> Self {
+        self.images.lock().unwrap().extend(images);
+        self
+    }
+
+    /// Returns the messages of all calls so far.
+    pub fn calls(&self) -> Vec<Vec<Message>> {
+        self.calls.lock().unwrap().clone()
+    }
+
+    /// Returns the number of responses not consumed yet.
+    pub fn remaining(&self) -> usize {
+        self.responses.lock().unwrap().len()
+    }
+}
+
+#[async_trait]
+impl LM for ScriptedLM {
+    async fn call(&self, messages: Vec<Message>, _schema: Option<Schema>) -> Result<String, Error> {
+        let mut calls = self.calls.lock().unwrap();
+        calls.push(messages);
+        self.responses.lock().unwrap().pop_front().ok_or_else(|| {
+            Error::ModelCall(format!(
+                "ScriptedLM: no response left for call {}",
+                calls.len()
+            ))
+        })
+    }
+
+    async fn generate_images(
+        &self,
+        messages: Vec<Message>,
+        n: usize,
+    ) -> Result<Vec<MessageContent>, Error> {
+        let mut calls = self.calls.lock().unwrap();
+        calls.push(messages);
+        let mut images = self.images.lock().unwrap();
+        if images.len() < n {
+            return Err(Error::ModelCall(format!(
+                "ScriptedLM: {} images left for call {}, {n} requested",
+                images.len(),
+                calls.len()
+            )));
+        }
+        Ok(images
+            .drain(..n)
+            .map(|image| MessageContent::Image { url: image.url })
+            .collect())
+    }
+}
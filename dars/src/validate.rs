@@ -0,0 +1,326 @@
+use std::fmt::Display;
+
+use regex::Regex;
+use schemars::Schema;
+use serde_json::Value;
+use tracing::warn;
+
+/// Schema keywords that constrain values beyond their type.
+pub(crate) const CONSTRAINT_KEYWORDS: &[&str] = &[
+    "minimum",
+    "maximum",
+    "exclusiveMinimum",
+    "exclusiveMaximum",
+    "minLength",
+    "maxLength",
+    "pattern",
+    "minItems",
+    "maxItems",
+    "enum",
+    "const",
+];
+
+/// A value that does not satisfy a constraint of the output schema.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Violation {
+    /// Path to the offending value, e.g. `entities[0].name`. Empty for the root.
+    pub path: String,
+    /// Human (and LM) readable description of the violated constraint.
+    pub message: String,
+}
+
+impl Display for Violation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.path.is_empty() {
+            write!(f, "{}", self.message)
+        } else {
+            write!(f, "`{}` {}", self.path, self.message)
+        }
+    }
+}
+
+/// Validate `value` against the constraints in `schema`, returning all violations.
+///
+/// Checks types, value constraints (ranges, lengths, patterns, enums) and
+/// required properties. References to `$defs` are resolved against the root `schema`.
+pub fn validate(schema: &Schema, value: &Value) -> Vec<Violation> {
+    let mut violations = vec![];
+    Validator {
+        root: schema.as_value(),
+    }
+    .validate(schema.as_value(), value, "", &mut violations);
+    violations
+}
+
+struct Validator<'a> {
+    root: &'a Value,
+}
+
+impl Validator<'_> {
+    fn validate(&self, schema: &Value, value: &Value, path: &str, out: &mut Vec<Violation>) {
+        let schema = match schema {
+            Value::Bool(true) => return,
+            Value::Bool(false) => {
+                out.push(violation(path, "is not allowed"));
+                return;
+            }
+            Value::Object(schema) => schema,
+            _ => return,
+        };
+
+        if let Some(Value::String(r)) = schema.get("$ref") {
+            match r.strip_prefix('#').and_then(|p| self.root.pointer(p)) {
+                Some(resolved) => self.validate(resolved, value, path, out),
+                None => warn!("Unresolved schema reference: {r}"),
+            }
+        }
+
+        if let Some(Value::Array(all)) = schema.get("allOf") {
+            for s in all {
+                self.validate(s, value, path, out);
+            }
+        }
+        for key in ["anyOf", "oneOf"] {
+            if let Some(Value::Array(any)) = schema.get(key) {
+                self.validate_any(any, value, path, out);
+            }
+        }
+
+        if let Some(ty) = schema.get("type")
+            && !matches_type(ty, value)
+        {
+            out.push(violation(path, format!("must be of type {ty}")));
+            return;
+        }
+
+        if let Some(Value::Array(allowed)) = schema.get("enum")
+            && !allowed.contains(value)
+        {
+            out.push(violation(
+                path,
+                format!("must be one of {}", Value::Array(allowed.clone())),
+            ));
+        }
+        if let Some(expected) = schema.get("const")
+            && expected != value
+        {
+            out.push(violation(path, format!("must be {expected}")));
+        }
+
+        match value {
+            Value::Number(n) => {
+                let n = n.as_f64().unwrap_or_default();
+                if let Some(min) = number(schema.get("minimum"))
+                    && n < min
+                {
+                    out.push(violation(path, format!("must be >= {min}")));
+                }
+                if let Some(max) = number(schema.get("maximum"))
+                    && n > max
+                {
+                    out.push(violation(path, format!("must be <= {max}")));
+                }
+                if let Some(min) = number(schema.get("exclusiveMinimum"))
+                    && n <= min
+                {
+                    out.push(violation(path, format!("must be > {min}")));
+                }
+                if let Some(max) = number(schema.get("exclusiveMaximum"))
+                    && n >= max
+                {
+                    out.push(violation(path, format!("must be < {max}")));
+                }
+            }
+            Value::String(s) => {
+                let len = s.chars().count() as u64;
+                if let Some(min) = schema.get("minLength").and_then(Value::as_u64)
+                    && len < min
+                {
+                    out.push(violation(
+                        path,
+                        format!("must have at least {min} characters"),
+                    ));
+                }
+                if let Some(max) = schema.get("maxLength").and_then(Value::as_u64)
+                    && len > max
+                {
+                    out.push(violation(
+                        path,
+                        format!("must have at most {max} characters"),
+                    ));
+                }
+                if let Some(Value::String(pattern)) = schema.get("pattern") {
+                    match Regex::new(pattern) {
+                        Ok(re) if !re.is_match(s) => {
+                            out.push(violation(path, format!("must match pattern `{pattern}`")));
+                        }
+                        Ok(_) => {}
+                        Err(e) => warn!("Invalid pattern in schema: {pattern}: {e:?}"),
+                    }
+                }
+            }
+            Value::Array(items) => {
+                let len = items.len() as u64;
+                if let Some(min) = schema.get("minItems").and_then(Value::as_u64)
+                    && len < min
+                {
+                    out.push(violation(path, format!("must have at least {min} items")));
+                }
+                if let Some(max) = schema.get("maxItems").and_then(Value::as_u64)
+                    && len > max
+                {
+                    out.push(violation(path, format!("must have at most {max} items")));
+                }
+
+                let prefix = match schema.get("prefixItems") {
+                    Some(Value::Array(prefix)) => prefix.as_slice(),
+                    _ => &[],
+                };
+                for (i, item) in items.iter().enumerate() {
+                    let item_path = format!("{path}[{i}]");
+                    if let Some(s) = prefix.get(i).or(schema.get("items")) {
+                        self.validate(s, item, &item_path, out);
+                    }
+                }
+            }
+            Value::Object(kv) => {
+                if let Some(Value::Array(required)) = schema.get("required") {
+                    for name in required.iter().filter_map(Value::as_str) {
+                        if !kv.contains_key(name) {
+                            out.push(violation(&join(path, name), "is required"));
+                        }
+                    }
+                }
+
+                let properties = schema.get("properties").and_then(Value::as_object);
+                for (name, v) in kv {
+                    match properties.and_then(|p| p.get(name)) {
+                        Some(s) => self.validate(s, v, &join(path, name), out),
+                        None => {
+                            if let Some(s) = schema.get("additionalProperties") {
+                                self.validate(s, v, &join(path, name), out);
+                            }
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Validate against a list of alternatives, reporting the violations of
+    /// the closest alternative if none of them matches.
+    fn validate_any(&self, any: &[Value], value: &Value, path: &str, out: &mut Vec<Violation>) {
+        let mut closest: Option<Vec<Violation>> = None;
+        for s in any {
+            let mut violations = vec![];
+            self.validate(s, value, path, &mut violations);
+            if violations.is_empty() {
+                return;
+            }
+            if closest.as_ref().is_none_or(|c| violations.len() < c.len()) {
+                closest = Some(violations);
+            }
+        }
+        out.extend(closest.unwrap_or_default());
+    }
+}
+
+fn violation(path: &str, message: impl Into<String>) -> Violation {
+    Violation {
+        path: path.to_string(),
+        message: message.into(),
+    }
+}
+
+fn matches_type(ty: &Value, value: &Value) -> bool {
+    match ty {
+        Value::String(ty) => match ty.as_str() {
+            "null" => value.is_null(),
+            "boolean" => value.is_boolean(),
+            "string" => value.is_string(),
+            "integer" => value.is_i64() || value.is_u64(),
+            "number" => value.is_number(),
+            "array" => value.is_array(),
+            "object" => value.is_object(),
+            _ => true,
+        },
+        Value::Array(types) => types.iter().any(|ty| matches_type(ty, value)),
+        _ => true,
+    }
+}
+
+fn number(value: Option<&Value>) -> Option<f64> {
+    value.and_then(Value::as_f64)
+}
+
+fn join(path: &str, name: &str) -> String {
+    if path.is_empty() {
+        name.to_string()
+    } else {
+        format!("{path}.{name}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+    use schemars::json_schema;
+    use serde_json::json;
+
+    #[rstest]
+    #[case(json_schema!({"type": "number", "minimum": 0, "maximum": 1}), json!(0.5), vec![])]
+    #[case(json_schema!({"type": "number", "minimum": 0, "maximum": 1}), json!(1.5), vec!["must be <= 1"])]
+    #[case(json_schema!({"type": "integer", "exclusiveMinimum": 0}), json!(0), vec!["must be > 0"])]
+    #[case(json_schema!({"type": "string", "maxLength": 3}), json!("abcd"), vec!["must have at most 3 characters"])]
+    #[case(json_schema!({"type": "string", "pattern": "^[a-z]+$"}), json!("ab1"), vec!["must match pattern `^[a-z]+$`"])]
+    #[case(json_schema!({"type": "array", "minItems": 2}), json!([1]), vec!["must have at least 2 items"])]
+    #[case(json_schema!({"enum": ["a", "b"]}), json!("c"), vec!["must be one of [\"a\",\"b\"]"])]
+    #[case(json_schema!({"type": ["string", "null"], "minLength": 2}), json!(null), vec![])]
+    fn test_validate_keywords(
+        #[case] schema: Schema,
+        #[case] value: Value,
+        #[case] expected: Vec<&str>,
+    ) {
+        let messages = validate(&schema, &value)
+            .into_iter()
+            .map(|v| v.message)
+            .collect::<Vec<_>>();
+        assert_eq!(messages, expected);
+    }
+
+    #[test]
+    fn test_validate_nested_paths() {
+        let schema = json_schema!({
+            "type": "object",
+            "properties": {
+                "items": {"type": "array", "items": {"$ref": "#/$defs/Item"}},
+                "score": {"anyOf": [{"type": "number", "maximum": 10}, {"type": "null"}]}
+            },
+            "required": ["items", "label"],
+            "$defs": {
+                "Item": {
+                    "type": "object",
+                    "properties": {"name": {"type": "string", "minLength": 1}}
+                }
+            }
+        });
+
+        let violations = validate(
+            &schema,
+            &json!({"items": [{"name": "a"}, {"name": ""}], "score": 11}),
+        );
+        assert_eq!(
+            violations
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            vec![
+                "`label` is required",
+                "`items[1].name` must have at least 1 characters",
+                "`score` must be <= 10",
+            ]
+        );
+    }
+}
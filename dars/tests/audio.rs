@@ -0,0 +1,102 @@
+use std::sync::Arc;
+
+use serde_json::json;
+
+use da_rs::lm::{Message, MessageContent};
+use da_rs::testing::ScriptedLM;
+use da_rs::*;
+
+const WAV_HEADER: &[u8] = b"RIFF\x24\x00\x00\x00WAVEfmt ";
+const MP3_HEADER: &[u8] = b"ID3\x04\x00\x00\x00\x00\x00\x00";
+
+#[test]
+fn test_from_bytes() {
+    let audio = Audio::from_bytes(WAV_HEADER).unwrap();
+    assert_eq!(audio.format, "wav");
+    assert_eq!(audio.mime_type(), "audio/wav");
+
+    let audio = Audio::from_bytes(MP3_HEADER).unwrap();
+    assert_eq!(audio.format, "mp3");
+    assert_eq!(audio.mime_type(), "audio/mpeg");
+
+    assert_eq!(Audio::from_bytes([0xFF, 0xFB, 0x90]).unwrap().format, "mp3");
+    // AAC ADTS shares the frame sync of MPEG audio
+    assert!(Audio::from_bytes([0xFF, 0xF1, 0x50]).is_err());
+    assert_eq!(Audio::from_bytes(b"fLaC\x00").unwrap().format, "flac");
+    assert_eq!(Audio::from_bytes(b"OggS\x00").unwrap().format, "ogg");
+
+    let err = Audio::from_bytes(b"plain text").unwrap_err();
+    assert!(matches!(err, Error::InvalidArgument(_)));
+}
+
+#[test]
+fn test_from_path_and_base64() {
+    let path = std::env::temp_dir().join(format!("dars-{}-audio.wav", std::process::id()));
+    std::fs::write(&path, WAV_HEADER).unwrap();
+    let audio = Audio::from_path(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(audio, Audio::from_bytes(WAV_HEADER).unwrap());
+
+    assert_eq!(Audio::from_base64(&audio.data).unwrap(), audio);
+    let url = format!("data:audio/wav;base64,{}", audio.data);
+    assert_eq!(Audio::from_base64(&url).unwrap(), audio);
+
+    let value = serde_json::to_value(&audio).unwrap();
+    assert_eq!(value, json!({"data": audio.data, "format": "wav"}));
+    assert_eq!(serde_json::from_value::<Audio>(value).unwrap(), audio);
+}
+
+#[Signature]
+struct Transcribe {
+    #[input]
+    recording: Audio,
+
+    #[output]
+    transcript: String,
+}
+
+#[tokio::test]
+async fn test_audio_part() {
+    let lm = Arc::new(ScriptedLM::json([json!({"transcript": "hello"})]));
+    let predict = Predict::new(lm.clone(), Transcribe::new());
+    let audio = Audio::from_bytes(WAV_HEADER).unwrap();
+    predict
+        .call(TranscribeInput {
+            recording: audio.clone(),
+        })
+        .await
+        .unwrap();
+
+    let Message::User { content } = &lm.calls()[0][1] else {
+        panic!("expected a user message");
+    };
+    assert_eq!(
+        content[1],
+        MessageContent::Audio {
+            data: audio.data,
+            format: "wav".to_string(),
+        }
+    );
+}
+
+#[cfg(feature = "openai")]
+#[test]
+fn test_openai_audio() {
+    use async_openai::types::chat::ChatCompletionRequestMessage;
+
+    let message = |format: &str| Message::User {
+        content: vec![MessageContent::Audio {
+            data: "AAAA".to_string(),
+            format: format.to_string(),
+        }],
+    };
+
+    let request = ChatCompletionRequestMessage::try_from(message("wav")).unwrap();
+    assert_eq!(
+        serde_json::to_value(request).unwrap()["content"][0],
+        json!({"type": "input_audio", "input_audio": {"data": "AAAA", "format": "wav"}})
+    );
+
+    let err = ChatCompletionRequestMessage::try_from(message("flac")).unwrap_err();
+    assert!(matches!(err, Error::InvalidArgument(msg) if msg.contains("flac")));
+}
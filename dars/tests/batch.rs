@@ -0,0 +1,97 @@
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use async_trait::async_trait;
+
+use da_rs::*;
+
+/// Module sleeping for `input` milliseconds, failing on odd inputs.
+struct Sleep {
+    in_flight: AtomicUsize,
+    max_in_flight: AtomicUsize,
+}
+
+impl Sleep {
+    fn new() -> Self {
+        Self {
+            in_flight: AtomicUsize::new(0),
+            max_in_flight: AtomicUsize::new(0),
+        }
+    }
+}
+
+#[async_trait]
+impl Module for Sleep {
+    type Input = u64;
+    type Output = u64;
+
+    async fn call(&self, input: u64) -> Result<u64, Error> {
+        let n = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+        self.max_in_flight.fetch_max(n, Ordering::SeqCst);
+        tokio::time::sleep(Duration::from_millis(input)).await;
+        self.in_flight.fetch_sub(1, Ordering::SeqCst);
+
+        if input % 2 == 1 {
+            return Err(Error::ModelCall(format!("odd input {input}")));
+        }
+        Ok(input)
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_batch_preserves_order_and_captures_errors() {
+    let module = Sleep::new();
+    let progress = Arc::new(Mutex::new(vec![]));
+
+    let results = module
+        .batch(
+            vec![40, 10, 30, 20],
+            BatchConfig::concurrency(2).with_progress({
+                let progress = progress.clone();
+                move |p| progress.lock().unwrap().push(p)
+            }),
+        )
+        .await;
+
+    let results = results.into_iter().map(|r| r.ok()).collect::<Vec<_>>();
+    assert_eq!(results, vec![Some(40), Some(10), Some(30), Some(20)]);
+    assert_eq!(module.max_in_flight.load(Ordering::SeqCst), 2);
+
+    let progress = progress.lock().unwrap();
+    assert_eq!(progress.len(), 4);
+    assert_eq!(
+        progress[3],
+        Progress {
+            completed: 4,
+            failed: 0,
+            total: 4
+        }
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_batch_per_item_errors() {
+    let module = Sleep::new();
+    let results = module.batch(vec![2, 3, 4], BatchConfig::default()).await;
+
+    assert!(matches!(results[0], Ok(2)));
+    assert!(matches!(&results[1], Err(Error::ModelCall(msg)) if msg == "odd input 3"));
+    assert!(matches!(results[2], Ok(4)));
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_batch_fail_fast() {
+    let module = Sleep::new();
+    let results = module
+        .batch(
+            vec![1, 100, 200],
+            BatchConfig::concurrency(2).with_fail_fast(true),
+        )
+        .await;
+
+    assert!(matches!(results[0], Err(Error::ModelCall(_))));
+    assert!(matches!(results[1], Err(Error::Cancelled)));
+    assert!(matches!(results[2], Err(Error::Cancelled)));
+}
@@ -0,0 +1,268 @@
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use serde_json::{Value, json};
+
+use da_rs::callback::{CallInfo, Trace, TraceCollector, TraceEvent};
+use da_rs::testing::ScriptedLM;
+use da_rs::*;
+
+#[Signature]
+struct Sig {
+    #[input]
+    question: String,
+
+    #[output]
+    answer: String,
+}
+
+fn input(question: &str) -> SigInput {
+    SigInput {
+        question: question.to_string(),
+    }
+}
+
+/// Module answering a question twice using two predictors.
+struct Pipeline {
+    first: Predict<Sig>,
+    second: Predict<Sig>,
+}
+
+#[async_trait]
+impl Module for Pipeline {
+    type Input = SigInput;
+    type Output = SigOutput;
+
+    async fn call(&self, input: SigInput) -> Result<SigOutput, Error> {
+        traced("Pipeline", input, |input| async move {
+            let first = self.first.call(input).await?;
+            self.second.call(self::input(&first.answer)).await
+        })
+        .await
+    }
+}
+
+fn kinds(trace: &Trace) -> Vec<&'static str> {
+    trace
+        .children
+        .iter()
+        .map(|c| match c.event {
+            TraceEvent::Module { .. } => "module",
+            TraceEvent::Lm { .. } => "lm",
+            TraceEvent::AdapterFormat { .. } => "format",
+            TraceEvent::AdapterParse { .. } => "parse",
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn test_trace_tree() {
+    let lm = Arc::new(ScriptedLM::json([
+        json!({"answer": "first"}),
+        json!({"answer": "second"}),
+    ]));
+    let pipeline = Pipeline {
+        first: Predict::new(lm.clone(), Sig::new()),
+        second: Predict::new(lm.clone(), Sig::new()),
+    };
+
+    let collector = Arc::new(TraceCollector::new());
+    let output = CallContext::current()
+        .with_callback(collector.clone())
+        .scope(pipeline.call(input("question")))
+        .await
+        .unwrap();
+    assert_eq!(output.answer, "second");
+
+    let traces = collector.traces();
+    assert_eq!(traces.len(), 1);
+
+    let root = &traces[0];
+    assert_eq!(
+        root.event,
+        TraceEvent::Module {
+            name: "Pipeline".to_string(),
+            input: json!({"question": "question"}),
+            output: Some(Ok(json!({"answer": "second"}))),
+        }
+    );
+    assert!(root.duration.is_some());
+    assert_eq!(kinds(root), vec!["module", "module"]);
+
+    let predict = &root.children[1];
+    let TraceEvent::Module { name, input, .. } = &predict.event else {
+        panic!("expected a module event");
+    };
+    assert_eq!(name, "Predict<Sig>");
+    assert_eq!(input, &json!({"question": "first"}));
+    assert_eq!(kinds(predict), vec!["format", "lm", "parse"]);
+
+    let TraceEvent::AdapterFormat { messages, .. } = &predict.children[0].event else {
+        panic!("expected a format event");
+    };
+    let TraceEvent::Lm {
+        messages: lm_messages,
+        schema,
+        completion,
+    } = &predict.children[1].event
+    else {
+        panic!("expected an LM event");
+    };
+    assert_eq!(messages, lm_messages);
+    assert_eq!(lm.calls()[1], *lm_messages);
+    assert!(schema.is_some());
+    assert_eq!(
+        completion.as_ref().unwrap().as_deref(),
+        Ok(r#"{"answer":"second"}"#)
+    );
+    assert_eq!(
+        predict.children[2].event,
+        TraceEvent::AdapterParse {
+            completion: r#"{"answer":"second"}"#.to_string(),
+            output: Ok(json!({"answer": "second"})),
+        }
+    );
+}
+
+#[tokio::test]
+async fn test_trace_errors() {
+    let lm = Arc::new(ScriptedLM::new(["not json"]));
+    let predict = Predict::new(lm, Sig::new());
+
+    let collector = Arc::new(TraceCollector::new());
+    let ctx = CallContext::current().with_callback(collector.clone());
+    let err = ctx
+        .clone()
+        .scope(predict.call(input("q")))
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::SerdeJson(_)));
+    // The exhausted LM fails the second call
+    ctx.scope(predict.call(input("q"))).await.unwrap_err();
+
+    let traces = collector.traces();
+    assert_eq!(traces.len(), 2);
+
+    let TraceEvent::Module { output, .. } = &traces[0].event else {
+        panic!("expected a module event");
+    };
+    assert!(output.as_ref().unwrap().is_err());
+    assert_eq!(kinds(&traces[0]), vec!["format", "lm", "parse"]);
+    assert!(matches!(
+        &traces[0].children[2].event,
+        TraceEvent::AdapterParse { output: Err(_), .. }
+    ));
+
+    assert_eq!(kinds(&traces[1]), vec!["format", "lm"]);
+    let TraceEvent::Lm { completion, .. } = &traces[1].children[1].event else {
+        panic!("expected an LM event");
+    };
+    assert!(completion.as_ref().unwrap().is_err());
+}
+
+#[tokio::test]
+async fn test_trace_batch() {
+    let lm = Arc::new(ScriptedLM::json([
+        json!({"answer": "a"}),
+        json!({"answer": "b"}),
+        json!({"answer": "c"}),
+    ]));
+    let predict = Predict::new(lm, Sig::new());
+
+    let collector = Arc::new(TraceCollector::new());
+    let inputs = vec![input("1"), input("2"), input("3")];
+    CallContext::current()
+        .with_callback(collector.clone())
+        .scope(predict.batch(inputs, BatchConfig::default()))
+        .await;
+
+    let traces = collector.traces();
+    assert_eq!(traces.len(), 3);
+    for trace in &traces {
+        assert_eq!(kinds(trace), vec!["format", "lm", "parse"]);
+    }
+
+    collector.clear();
+    assert!(collector.traces().is_empty());
+}
+
+#[tokio::test]
+async fn test_trace_rollouts() {
+    let lm = Arc::new(ScriptedLM::json([
+        json!({"answer": "short"}),
+        json!({"answer": "longer"}),
+        json!({"answer": "a"}),
+        json!({"answer": "abc"}),
+    ]));
+    let reward = |_: &SigInput, o: &SigOutput| o.answer.len() as f64;
+    let best_of_n = BestOfN::new(Predict::new(lm.clone(), Sig::new()), 2, reward);
+    let refine = Refine::new(Predict::new(lm, Sig::new()), 2, reward, 3.0);
+
+    let collector = Arc::new(TraceCollector::new());
+    let ctx = CallContext::current().with_callback(collector.clone());
+    let output = ctx.clone().scope(best_of_n.call(input("q"))).await.unwrap();
+    assert_eq!(output.answer, "longer");
+    let output = ctx.scope(refine.call(input("q"))).await.unwrap();
+    assert_eq!(output.answer, "abc");
+
+    // The attempts are nested under the module call
+    let traces = collector.traces();
+    assert_eq!(traces.len(), 2);
+    for (trace, module, answer) in [
+        (&traces[0], "BestOfN", "longer"),
+        (&traces[1], "Refine", "abc"),
+    ] {
+        assert_eq!(
+            trace.event,
+            TraceEvent::Module {
+                name: module.to_string(),
+                input: json!({"question": "q"}),
+                output: Some(Ok(json!({"answer": answer}))),
+            }
+        );
+        assert_eq!(kinds(trace), vec!["module", "module"]);
+        for attempt in &trace.children {
+            assert_eq!(kinds(attempt), vec!["format", "lm", "parse"]);
+        }
+    }
+}
+
+/// Callback recording the events it receives.
+#[derive(Default)]
+struct EventLog {
+    events: Mutex<Vec<(String, CallInfo)>>,
+}
+
+impl Callback for EventLog {
+    fn on_module_start(&self, call: &CallInfo, name: &str, _input: &Value) {
+        let event = format!("module_start {name}");
+        self.events.lock().unwrap().push((event, *call));
+    }
+
+    fn on_lm_end(&self, call: &CallInfo, _completion: Result<&str, &Error>) {
+        self.events.lock().unwrap().push(("lm_end".into(), *call));
+    }
+}
+
+#[tokio::test]
+async fn test_custom_callback() {
+    let lm = Arc::new(ScriptedLM::json([json!({"answer": "a"})]));
+    let predict = Predict::new(lm, Sig::new());
+
+    let log = Arc::new(EventLog::default());
+    CallContext::current()
+        .with_callback(log.clone())
+        .scope(predict.call(input("q")))
+        .await
+        .unwrap();
+
+    let events = log.events.lock().unwrap();
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0].0, "module_start Predict<Sig>");
+    assert_eq!(events[0].1.parent, None);
+    assert_eq!(events[1].0, "lm_end");
+    assert_eq!(events[1].1.parent, Some(events[0].1.id));
+
+    // Callbacks are only attached to the scoped call
+    assert!(CallContext::current().callbacks.is_empty());
+}
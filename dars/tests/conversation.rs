@@ -0,0 +1,176 @@
+use std::sync::Arc;
+
+use serde_json::json;
+
+use da_rs::lm::{Message, MessageContent};
+use da_rs::testing::ScriptedLM;
+use da_rs::*;
+
+#[Signature("Answer the question.")]
+struct Chat {
+    #[input]
+    question: String,
+
+    #[input]
+    history: History,
+
+    #[output]
+    answer: String,
+}
+
+fn text(message: &Message) -> String {
+    match message {
+        Message::System { instruction } => instruction.clone(),
+        Message::User { content } => content.iter().map(ToString::to_string).collect(),
+        Message::Assistant { content } => content.to_string(),
+    }
+}
+
+#[test]
+fn test_history_schema() {
+    let chat = Chat::new();
+    assert!(History::is_history(chat.field("history").unwrap()));
+    assert!(!History::is_history(chat.field("question").unwrap()));
+}
+
+#[test]
+fn test_history_push() {
+    let mut history = History::new();
+    history
+        .push(&json!({"question": "Hi"}), &json!({"answer": "Hello"}))
+        .unwrap();
+    assert_eq!(history.len(), 1);
+    assert_eq!(history.turns[0].input["question"], "Hi");
+    assert_eq!(history.turns[0].output["answer"], "Hello");
+
+    let err = history.push(&"not an object", &json!({})).unwrap_err();
+    assert!(matches!(err, Error::InvalidArgument(_)));
+    assert_eq!(history.len(), 1);
+}
+
+#[tokio::test]
+async fn test_history_turns_do_not_nest() {
+    let lm = Arc::new(ScriptedLM::json(
+        (0..4).map(|i| json!({"answer": format!("answer {i}")})),
+    ));
+    let chat = Predict::new(lm, Chat::new());
+
+    let mut history = History::new();
+    for i in 0..4 {
+        let input = ChatInput {
+            question: format!("question {i}"),
+            history: history.clone(),
+        };
+        let output = chat.call(input.clone()).await.unwrap();
+        history.push(&input, &output).unwrap();
+    }
+
+    // Each turn keeps its own fields only, not the earlier turns
+    assert_eq!(history.len(), 4);
+    for (i, turn) in history.turns.iter().enumerate() {
+        assert_eq!(turn.input.len(), 1);
+        assert_eq!(turn.input["question"], format!("question {i}"));
+        assert!(!turn.input.contains_key("history"));
+    }
+    let turn = serde_json::to_string(&history.turns[3]).unwrap();
+    assert!(!turn.contains("question 2"));
+}
+
+#[tokio::test]
+async fn test_history_turns() {
+    let lm = Arc::new(ScriptedLM::json([
+        json!({"answer": "Paris"}),
+        json!({"answer": "About 2 million"}),
+    ]));
+    let chat = Predict::new(lm.clone(), Chat::new());
+
+    // First turn
+    let mut history = History::new();
+    let input = ChatInput {
+        question: "What is the capital of France?".to_string(),
+        history: history.clone(),
+    };
+    let output = chat.call(input.clone()).await.unwrap();
+    history.push(&input, &output).unwrap();
+
+    // Second turn
+    let input = ChatInput {
+        question: "How many people live there?".to_string(),
+        history: history.clone(),
+    };
+    let output = chat.call(input).await.unwrap();
+    assert_eq!(output.answer, "About 2 million");
+
+    let calls = lm.calls();
+    assert_eq!(calls[0].len(), 2);
+
+    let messages = &calls[1];
+    assert_eq!(messages.len(), 4);
+    assert!(matches!(messages[0], Message::System { .. }));
+    assert!(!text(&messages[0]).contains("history"));
+    assert_eq!(
+        text(&messages[1]),
+        "[[ ## question ## ]]\n\"What is the capital of France?\""
+    );
+    assert_eq!(
+        messages[2],
+        Message::Assistant {
+            content: MessageContent::Text {
+                text: r#"{"answer": "Paris"}"#.to_string()
+            }
+        }
+    );
+    assert_eq!(
+        text(&messages[3]),
+        "[[ ## question ## ]]\n\"How many people live there?\""
+    );
+}
+
+#[Signature("Describe the photo.")]
+struct Describe {
+    #[input]
+    photo: Image,
+
+    #[input]
+    history: History,
+
+    #[output]
+    description: String,
+}
+
+#[tokio::test]
+async fn test_history_turns_with_images() {
+    let url = "data:image/png;base64,iVBORw0KGgo=";
+    let lm = Arc::new(ScriptedLM::json([json!({"description": "A cat"})]));
+    let describe = Predict::new(lm.clone(), Describe::new());
+
+    let history = History::new()
+        .with_turn(
+            &DescribeInput {
+                photo: Image::from_url(url),
+                history: History::new(),
+            },
+            &json!({"description": "A dog"}),
+        )
+        .unwrap();
+    assert_eq!(history.turns[0].media.len(), 1);
+
+    describe
+        .call(DescribeInput {
+            photo: Image::from_url("https://example.com/cat.png"),
+            history,
+        })
+        .await
+        .unwrap();
+
+    // The image of the prior turn is sent as a message part, not inlined
+    let messages = &lm.calls()[0];
+    assert_eq!(messages.len(), 4);
+    let Message::User { content } = &messages[1] else {
+        panic!("expected a user message");
+    };
+    assert!(content.contains(&MessageContent::Image {
+        url: url.to_string()
+    }));
+    assert!(!text(&messages[1]).contains("base64"));
+}
@@ -0,0 +1,281 @@
+use std::sync::Arc;
+
+use serde_json::json;
+
+use da_rs::lm::{Message, MessageContent};
+use da_rs::testing::ScriptedLM;
+use da_rs::*;
+
+/// Builds a PDF with one page per text.
+fn pdf(texts: &[&str]) -> Vec<u8> {
+    use lopdf::content::{Content, Operation};
+    use lopdf::{Object, Stream, dictionary};
+
+    let mut doc = lopdf::Document::with_version("1.5");
+    let pages_id = doc.new_object_id();
+    let font_id = doc.add_object(dictionary! {
+        "Type" => "Font",
+        "Subtype" => "Type1",
+        "BaseFont" => "Courier",
+    });
+    let resources_id = doc.add_object(dictionary! {
+        "Font" => dictionary! { "F1" => font_id },
+    });
+
+    let mut kids = vec![];
+    for text in texts {
+        let content = Content {
+            operations: vec![
+                Operation::new("BT", vec![]),
+                Operation::new("Tf", vec!["F1".into(), 12.into()]),
+                Operation::new("Td", vec![100.into(), 600.into()]),
+                Operation::new("Tj", vec![Object::string_literal(*text)]),
+                Operation::new("ET", vec![]),
+            ],
+        };
+        let content_id = doc.add_object(Stream::new(dictionary! {}, content.encode().unwrap()));
+        let page_id = doc.add_object(dictionary! {
+            "Type" => "Page",
+            "Parent" => pages_id,
+            "Contents" => content_id,
+        });
+        kids.push(page_id.into());
+    }
+
+    doc.objects.insert(
+        pages_id,
+        Object::Dictionary(dictionary! {
+            "Type" => "Pages",
+            "Count" => kids.len() as i64,
+            "Kids" => kids,
+            "Resources" => resources_id,
+            "MediaBox" => vec![0.into(), 0.into(), 595.into(), 842.into()],
+        }),
+    );
+    let catalog_id = doc.add_object(dictionary! {
+        "Type" => "Catalog",
+        "Pages" => pages_id,
+    });
+    doc.trailer.set("Root", catalog_id);
+
+    let mut buf = vec![];
+    doc.save_to(&mut buf).unwrap();
+    buf
+}
+
+#[test]
+fn test_text_pages() {
+    let doc = Document::from_text("first\x0Csecond\x0Cthird");
+    assert_eq!(doc.page_count().unwrap(), 3);
+
+    let doc = doc.with_pages(2..).unwrap();
+    assert_eq!(
+        doc.content,
+        DocumentContent::Pages {
+            pages: vec![
+                Page {
+                    number: 2,
+                    text: "second".to_string(),
+                },
+                Page {
+                    number: 3,
+                    text: "third".to_string(),
+                },
+            ],
+        }
+    );
+
+    let value = serde_json::to_value(&doc).unwrap();
+    assert_eq!(value["content"]["type"], "pages");
+    assert_eq!(serde_json::from_value::<Document>(value).unwrap(), doc);
+}
+
+#[test]
+fn test_from_bytes() {
+    let doc = Document::from_bytes("hello").unwrap();
+    assert_eq!(doc, Document::from_text("hello"));
+
+    let doc = Document::from_bytes(b"%PDF-1.5\n").unwrap();
+    assert!(matches!(
+        doc.content,
+        DocumentContent::File { ref mime_type, .. } if mime_type == "application/pdf"
+    ));
+
+    let err = Document::from_bytes([0xFF, 0xFE, 0x00]).unwrap_err();
+    assert!(matches!(err, Error::InvalidArgument(_)));
+}
+
+#[test]
+fn test_from_path() {
+    let path = std::env::temp_dir().join(format!("dars-{}-notes.txt", std::process::id()));
+    std::fs::write(&path, "notes").unwrap();
+    let doc = Document::from_path(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(
+        doc.filename,
+        path.file_name().and_then(|f| f.to_str()).map(String::from)
+    );
+    assert_eq!(doc.page_count().unwrap(), 1);
+}
+
+#[Signature]
+struct Summarize {
+    #[input]
+    document: Document,
+
+    #[output]
+    summary: String,
+}
+
+#[tokio::test]
+async fn test_document_parts() {
+    let lm = Arc::new(ScriptedLM::json([
+        json!({"summary": "a"}),
+        json!({"summary": "b"}),
+    ]));
+    let predict = Predict::new(lm.clone(), Summarize::new());
+
+    let text = Document::from_text("first\x0Csecond");
+    predict
+        .call(SummarizeInput { document: text })
+        .await
+        .unwrap();
+
+    let pdf = Document::from_bytes(b"%PDF-1.5\n")
+        .unwrap()
+        .with_filename("report.pdf");
+    predict
+        .call(SummarizeInput {
+            document: pdf.clone(),
+        })
+        .await
+        .unwrap();
+
+    let calls = lm.calls();
+
+    // Text pages are inlined in the message
+    let Message::User { content } = &calls[0][1] else {
+        panic!("expected a user message");
+    };
+    let [MessageContent::Text { text }] = content.as_slice() else {
+        panic!("expected a single text part, got {content:?}");
+    };
+    assert!(text.contains("--- Page 1 ---\\nfirst\\n\\n--- Page 2 ---\\nsecond"));
+
+    // PDFs are passed as file parts
+    let Message::User { content } = &calls[1][1] else {
+        panic!("expected a user message");
+    };
+    let DocumentContent::File { data, .. } = pdf.content else {
+        unreachable!()
+    };
+    assert_eq!(
+        content[1],
+        MessageContent::File {
+            data,
+            mime_type: "application/pdf".to_string(),
+            filename: Some("report.pdf".to_string()),
+        }
+    );
+}
+
+#[cfg(feature = "pdf")]
+#[test]
+fn test_pdf_pages() {
+    let doc = Document::from_bytes(pdf(&["one", "two", "three"])).unwrap();
+    assert_eq!(doc.page_count().unwrap(), 3);
+
+    let doc = doc.with_pages(2..=3).unwrap();
+    assert_eq!(doc.page_count().unwrap(), 2);
+
+    let DocumentContent::Pages { pages } = doc.into_text().unwrap().content else {
+        panic!("expected text pages");
+    };
+    let texts = pages.iter().map(|p| p.text.trim()).collect::<Vec<_>>();
+    assert_eq!(texts, ["two", "three"]);
+}
+
+#[cfg(feature = "pdf")]
+#[tokio::test]
+async fn test_pdf_text_fallback() {
+    use async_trait::async_trait;
+    use da_rs::lm::LM;
+    use schemars::Schema;
+
+    /// LM rejecting file parts.
+    struct TextOnlyLM(ScriptedLM);
+
+    #[async_trait]
+    impl LM for TextOnlyLM {
+        async fn call(
+            &self,
+            messages: Vec<Message>,
+            schema: Option<Schema>,
+        ) -> Result<String, Error> {
+            let has_file = messages.iter().any(|m| match m {
+                Message::User { content } => content
+                    .iter()
+                    .any(|c| matches!(c, MessageContent::File { .. })),
+                _ => false,
+            });
+            if has_file {
+                return Err(Error::UnsupportedContent("no file input".into()));
+            }
+            self.0.call(messages, schema).await
+        }
+    }
+
+    let lm = Arc::new(TextOnlyLM(ScriptedLM::json([json!({"summary": "a"})])));
+    let output = Predict::new(lm.clone(), Summarize::new())
+        .call(SummarizeInput {
+            document: Document::from_bytes(pdf(&["one", "two"])).unwrap(),
+        })
+        .await
+        .unwrap();
+    assert_eq!(output.summary, "a");
+
+    // The LM got the text of the pages instead of the file
+    let calls = lm.0.calls();
+    assert_eq!(calls.len(), 1);
+    let text = calls[0][1].to_string();
+    assert!(text.contains("--- Page 1 ---\none"));
+    assert!(text.contains("--- Page 2 ---\ntwo"));
+}
+
+#[cfg(not(feature = "pdf"))]
+#[test]
+fn test_pdf_pages_require_feature() {
+    let doc = Document::from_bytes(pdf(&["one"])).unwrap();
+    let err = doc.with_pages(1..=1).unwrap_err();
+    assert!(matches!(err, Error::InvalidArgument(msg) if msg.contains("`pdf` feature")));
+}
+
+#[cfg(feature = "openai")]
+#[test]
+fn test_openai_file() {
+    use async_openai::types::chat::ChatCompletionRequestMessage;
+
+    let message = |mime_type: &str| Message::User {
+        content: vec![MessageContent::File {
+            data: "AAAA".to_string(),
+            mime_type: mime_type.to_string(),
+            filename: None,
+        }],
+    };
+
+    let request = ChatCompletionRequestMessage::try_from(message("application/pdf")).unwrap();
+    assert_eq!(
+        serde_json::to_value(request).unwrap()["content"][0],
+        json!({
+            "type": "file",
+            "file": {
+                "file_data": "data:application/pdf;base64,AAAA",
+                "filename": "document.pdf",
+            },
+        })
+    );
+
+    let err = ChatCompletionRequestMessage::try_from(message("text/csv")).unwrap_err();
+    assert!(matches!(err, Error::UnsupportedContent(msg) if msg.contains("text/csv")));
+}
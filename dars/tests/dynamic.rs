@@ -0,0 +1,223 @@
+use std::sync::Arc;
+
+use schemars::json_schema;
+use serde_json::{Map, Value, json};
+
+use da_rs::lm::{Message, MessageContent};
+use da_rs::testing::ScriptedLM;
+use da_rs::*;
+
+fn input(value: Value) -> Map<String, Value> {
+    let Value::Object(map) = value else {
+        panic!("expected an object");
+    };
+    map
+}
+
+#[tokio::test]
+async fn test_dynamic_signature() {
+    let sig = DynamicSignature::parse(
+        "question, context: list[str] -> answer: str, score: float, note: Optional[str]",
+    )
+    .unwrap()
+    .with_instruction("Answer the question from the context.");
+    assert_eq!(
+        sig.input_fields(),
+        &[
+            Field {
+                name: "question",
+                description: None
+            },
+            Field {
+                name: "context",
+                description: None
+            }
+        ]
+    );
+    assert_eq!(sig.output_fields().len(), 3);
+    assert_eq!(
+        sig.output_schema().as_value()["required"],
+        json!(["answer", "score"])
+    );
+
+    let lm = Arc::new(ScriptedLM::json([json!({"answer": "Paris", "score": 0.9})]));
+    let output = Predict::new(lm.clone(), sig)
+        .call(input(json!({
+            "question": "What is the capital of France?",
+            "context": ["Paris is the capital of France."],
+        })))
+        .await
+        .unwrap();
+    assert_eq!(output["answer"], "Paris");
+    assert_eq!(output["score"], 0.9);
+    assert!(!output.contains_key("note"));
+
+    let calls = lm.calls();
+    let Message::System { instruction } = &calls[0][0] else {
+        panic!("expected a system message");
+    };
+    assert!(instruction.contains("1. `question` (string): \n"));
+    assert!(instruction.contains("2. `context` (list[string]): \n"));
+    assert!(instruction.contains("2. `score` (number): \n"));
+    assert!(instruction.contains("3. `note` (string | null, optional): \n"));
+    assert!(instruction.ends_with(
+        "Answer the question from the context.\n\nReturn ONLY a valid JSON object.\n\
+        Do not include any text before or after the JSON.\nDo not use markdown.\n"
+    ));
+}
+
+#[tokio::test]
+async fn test_dynamic_signature_literal() {
+    let sig =
+        DynamicSignature::parse("review -> sentiment: Literal['positive', 'negative']").unwrap();
+    let lm = Arc::new(ScriptedLM::json([json!({"sentiment": " Positive"})]));
+    let output = Predict::new(lm, sig)
+        .call(input(json!({"review": "Great!"})))
+        .await
+        .unwrap();
+    assert_eq!(output["sentiment"], "positive");
+}
+
+#[tokio::test]
+async fn test_dynamic_signature_missing_fields() {
+    let sig = DynamicSignature::parse("question -> answer, score: int").unwrap();
+    let lm = Arc::new(ScriptedLM::json([json!({"answer": "4"})]));
+    let err = Predict::new(lm, sig)
+        .with_max_retries(0)
+        .call(input(json!({"question": "2 + 2?"})))
+        .await
+        .unwrap_err();
+    assert!(matches!(&err, Error::MissingFields(fields) if fields == &["score"]));
+}
+
+#[tokio::test]
+async fn test_dynamic_signature_from_fields() {
+    let sig = DynamicSignature::new(
+        [(
+            Field {
+                name: "text",
+                description: Some("Text to summarize"),
+            },
+            json_schema!({"type": "string"}),
+        )],
+        [(
+            Field {
+                name: "summary",
+                description: Some("One sentence summary"),
+            },
+            json_schema!({"type": "string", "maxLength": 20}),
+        )],
+    )
+    .unwrap();
+    assert_eq!(
+        sig.output_schema().as_value()["properties"]["summary"],
+        json!({"type": "string", "maxLength": 20, "description": "One sentence summary"})
+    );
+
+    let lm = Arc::new(ScriptedLM::json([
+        json!({"summary": "A summary that is far too long"}),
+    ]));
+    let err = Predict::new(lm, sig)
+        .with_max_retries(0)
+        .call(input(json!({"text": "..."})))
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::Validation(_)));
+}
+
+#[test]
+fn test_dynamic_signature_types() {
+    let sig = DynamicSignature::parse(
+        "a: int, b: bool, c: dict[str, list[int]], d: Any -> e: Image, f: Literal[1, 2]",
+    )
+    .unwrap();
+    let schema = |name: &str| sig.field(name).unwrap().as_value().clone();
+    assert_eq!(schema("a"), json!({"type": "integer"}));
+    assert_eq!(schema("b"), json!({"type": "boolean"}));
+    assert_eq!(
+        schema("c"),
+        json!({"type": "object", "additionalProperties": {"type": "array", "items": {"type": "integer"}}})
+    );
+    assert_eq!(schema("d"), json!(true));
+    assert!(Image::is_image(sig.field("e").unwrap()));
+    assert_eq!(schema("f"), json!({"enum": [1, 2]}));
+}
+
+#[tokio::test]
+async fn test_dynamic_signature_image_input() {
+    let sig = DynamicSignature::parse("photo: Image -> caption").unwrap();
+    let lm = Arc::new(ScriptedLM::json([json!({"caption": "A cat"})]));
+    let url = "data:image/png;base64,iVBORw0KGgo=";
+    let output = Predict::new(lm.clone(), sig)
+        .call(input(json!({"photo": url})))
+        .await
+        .unwrap();
+    assert_eq!(output["caption"], "A cat");
+
+    // The image is sent as a part rather than inlined in the text
+    let Message::User { content } = &lm.calls()[0][1] else {
+        panic!("expected a user message");
+    };
+    assert_eq!(
+        content[1],
+        MessageContent::Image {
+            url: url.to_string()
+        }
+    );
+    assert!(!lm.calls()[0][1].to_string().contains("base64"));
+}
+
+#[test]
+fn test_dynamic_signature_errors() {
+    for (signature, reason) in [
+        ("question answer", "expected `inputs -> outputs`"),
+        ("question ->", "expected at least one output"),
+        ("question -> answer: tensor", "unsupported type `tensor`"),
+        (
+            "question -> answer: list[str",
+            "unclosed `[` in type `list[str`",
+        ),
+        ("question -> question", "duplicate field `question`"),
+        (
+            "my question -> answer",
+            "invalid field name \"my question\"",
+        ),
+        ("q -> a: Literal[maybe]", "invalid literal `maybe`"),
+    ] {
+        let err = DynamicSignature::parse(signature).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!("invalid argument: invalid signature {signature:?}: {reason}")
+        );
+    }
+}
+
+#[test]
+fn test_dynamic_signature_interns_names() {
+    let first = DynamicSignature::parse("question -> answer").unwrap();
+    let second = DynamicSignature::parse("question -> answer").unwrap();
+    assert!(std::ptr::eq(
+        first.input_fields()[0].name,
+        second.input_fields()[0].name
+    ));
+}
+
+#[test]
+fn test_dynamic_signature_duplicate_fields() {
+    let field = |name| {
+        (
+            Field {
+                name,
+                description: None,
+            },
+            json_schema!({"type": "string"}),
+        )
+    };
+    for (inputs, outputs) in [
+        (vec![field("text"), field("text")], vec![field("summary")]),
+        (vec![field("text")], vec![field("text")]),
+    ] {
+        let err = DynamicSignature::new(inputs, outputs).unwrap_err();
+        assert_eq!(err.to_string(), "invalid argument: duplicate field `text`");
+    }
+}
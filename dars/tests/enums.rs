@@ -0,0 +1,96 @@
+use std::sync::Arc;
+
+use serde_json::json;
+
+use da_rs::lm::Message;
+use da_rs::testing::ScriptedLM;
+use da_rs::*;
+
+#[Model]
+enum Sentiment {
+    Positive,
+    #[serde(rename = "very negative")]
+    VeryNegative,
+}
+
+#[Model]
+#[serde(rename_all = "lowercase")]
+enum Topic {
+    /// Sports and games
+    Sports,
+    /// Anything else
+    Other,
+}
+
+#[Signature("Classify the review.")]
+struct Classify {
+    #[input]
+    review: String,
+
+    #[output]
+    sentiment: Sentiment,
+
+    #[output]
+    topic: Topic,
+}
+
+fn input() -> ClassifyInput {
+    ClassifyInput {
+        review: "Great match!".to_string(),
+    }
+}
+
+#[tokio::test]
+async fn test_enum_outputs() {
+    let lm = Arc::new(ScriptedLM::json([
+        json!({"sentiment": " POSITIVE ", "topic": "Sports"}),
+        json!({"sentiment": "Very Negative", "topic": "other"}),
+    ]));
+    let predict = Predict::new(lm.clone(), Classify::new());
+
+    let output = predict.call(input()).await.unwrap();
+    assert_eq!(output.sentiment, Sentiment::Positive);
+    assert_eq!(output.topic, Topic::Sports);
+
+    let output = predict.call(input()).await.unwrap();
+    assert_eq!(output.sentiment, Sentiment::VeryNegative);
+    assert_eq!(output.topic, Topic::Other);
+
+    let Message::System { instruction } = &lm.calls()[0][0] else {
+        panic!("expected a system message");
+    };
+    assert!(instruction.contains("1. `sentiment` (Literal['Positive', 'very negative'])"));
+    assert!(instruction.contains("2. `topic` (Literal['sports', 'other'])"));
+}
+
+#[tokio::test]
+async fn test_enum_output_from_prose() {
+    let lm = Arc::new(ScriptedLM::new([
+        "Sure: {\"sentiment\": \"positive\", \"topic\": \"SPORTS\"}",
+    ]));
+    let predict = Predict::new(lm, Classify::new());
+    let output = predict.call(input()).await.unwrap();
+    assert_eq!(output.sentiment, Sentiment::Positive);
+    assert_eq!(output.topic, Topic::Sports);
+}
+
+#[tokio::test]
+async fn test_unknown_enum_value() {
+    let lm = Arc::new(ScriptedLM::json([
+        json!({"sentiment": "neutral", "topic": "sports"}),
+    ]));
+    let predict = Predict::new(lm, Classify::new());
+    assert!(predict.call(input()).await.is_err());
+}
+
+#[test]
+fn test_enum_serde() {
+    assert_eq!(
+        serde_json::to_value(Sentiment::VeryNegative).unwrap(),
+        json!("very negative")
+    );
+    assert_eq!(
+        serde_json::from_value::<Topic>(json!("other")).unwrap(),
+        Topic::Other
+    );
+}
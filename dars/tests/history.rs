@@ -0,0 +1,108 @@
+use std::sync::Arc;
+
+use serde_json::json;
+
+use da_rs::lm::{InspectOptions, InteractionLog, Message, MessageContent, history};
+use da_rs::testing::ScriptedLM;
+use da_rs::*;
+
+#[Signature]
+struct Sig {
+    #[input]
+    question: String,
+
+    #[output]
+    answer: String,
+}
+
+fn messages(question: &str) -> Vec<Message> {
+    vec![
+        Message::System {
+            instruction: "Answer the question.".to_string(),
+        },
+        Message::User {
+            content: vec![
+                MessageContent::Text {
+                    text: question.to_string(),
+                },
+                MessageContent::Image {
+                    url: "https://example.com/image.png".to_string(),
+                },
+            ],
+        },
+    ]
+}
+
+#[test]
+fn test_history_is_bounded() {
+    let history = InteractionLog::new(2);
+    history.record(messages("first"), "1");
+    history.record(messages("second"), "2");
+    history.record(messages("third"), "3");
+
+    let last = history.last(5);
+    assert_eq!(last.len(), 2);
+    assert_eq!(last[0].messages, messages("second"));
+    assert_eq!(last[1].completion, "3");
+    assert_eq!(history.last(1)[0].completion, "3");
+
+    history.set_capacity(1);
+    assert_eq!(history.last(5).len(), 1);
+
+    history.set_capacity(0);
+    history.record(messages("fourth"), "4");
+    assert_eq!(history.last(5).len(), 0);
+}
+
+#[test]
+fn test_history_render() {
+    let history = InteractionLog::new(10);
+    history.record(messages("first"), "1");
+    history.record(messages("second"), "2");
+
+    let rendered = history.render(1, InspectOptions::default());
+    assert_eq!(
+        rendered,
+        "\n[1/1]\n\n\
+        System:\nAnswer the question.\n\n\
+        User:\nsecond\n<image len=29>\n\n\
+        Response:\n2\n"
+    );
+
+    let rendered = history.render(2, InspectOptions::default().with_full_images(true));
+    assert!(rendered.contains("[2/2]"));
+    assert!(rendered.contains("first"));
+    assert!(rendered.contains("<image url=https://example.com/image.png>"));
+    assert!(!rendered.contains('\x1b'));
+
+    let rendered = history.render(1, InspectOptions::default().with_colors(true));
+    assert!(rendered.contains("\x1b[35mSystem:\x1b[0m"));
+    assert!(rendered.contains("\x1b[34mUser:\x1b[0m"));
+    assert!(rendered.contains("\x1b[32m2\x1b[0m"));
+}
+
+#[tokio::test]
+async fn test_global_history() {
+    // Disabled by default
+    assert!(!history().is_enabled());
+    history().set_capacity(100);
+
+    let lm = Arc::new(ScriptedLM::json([json!({"answer": "history answer"})]));
+    let predict = Predict::new(lm.clone(), Sig::new());
+    predict
+        .call(SigInput {
+            question: "history question".to_string(),
+        })
+        .await
+        .unwrap();
+
+    let interaction = history()
+        .last(100)
+        .into_iter()
+        .find(|i| i.messages == lm.calls()[0])
+        .unwrap();
+    assert_eq!(
+        interaction.completion,
+        json!({"answer": "history answer"}).to_string()
+    );
+}
@@ -0,0 +1,221 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use schemars::Schema;
+use serde_json::json;
+
+use da_rs::lm::{LM, Message};
+use da_rs::testing::ScriptedLM;
+use da_rs::*;
+
+const PNG_HEADER: &[u8] = &[0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A, 0, 0, 0, 13];
+const JPEG_HEADER: &[u8] = &[0xFF, 0xD8, 0xFF, 0xE0, 0, 16, b'J', b'F', b'I', b'F'];
+
+#[test]
+fn test_from_bytes() {
+    let image = Image::from_bytes(PNG_HEADER).unwrap();
+    assert!(image.url.starts_with("data:image/png;base64,"));
+    assert_eq!(image.mime_type(), Some("image/png"));
+    assert_eq!(image.data().unwrap(), PNG_HEADER);
+
+    let image = Image::from_bytes(JPEG_HEADER).unwrap();
+    assert_eq!(image.mime_type(), Some("image/jpeg"));
+
+    let image = Image::from_bytes(b"GIF89a\x01\x00\x01\x00").unwrap();
+    assert_eq!(image.mime_type(), Some("image/gif"));
+
+    let image = Image::from_bytes(b"RIFF\x00\x00\x00\x00WEBPVP8 ").unwrap();
+    assert_eq!(image.mime_type(), Some("image/webp"));
+
+    let err = Image::from_bytes(b"plain text").unwrap_err();
+    assert!(matches!(err, Error::InvalidArgument(_)));
+}
+
+#[test]
+fn test_from_base64() {
+    let image = Image::from_bytes(PNG_HEADER).unwrap();
+    let data = image.url.split_once(',').unwrap().1;
+
+    assert_eq!(Image::from_base64(data).unwrap(), image);
+    assert_eq!(Image::from_base64(&image.url).unwrap(), image);
+
+    let err = Image::from_base64("not base64!").unwrap_err();
+    assert!(matches!(err, Error::InvalidArgument(_)));
+}
+
+#[test]
+fn test_from_path() {
+    let dir = std::env::temp_dir();
+    let path = dir.join(format!("dars-{}-image.bin", std::process::id()));
+    std::fs::write(&path, JPEG_HEADER).unwrap();
+    let image = Image::from_path(&path).unwrap();
+    assert_eq!(image.mime_type(), Some("image/jpeg"));
+    std::fs::remove_file(&path).unwrap();
+
+    // Falls back to the extension
+    let path = dir.join(format!("dars-{}-image.svg", std::process::id()));
+    std::fs::write(&path, "<svg xmlns=\"http://www.w3.org/2000/svg\"/>").unwrap();
+    let image = Image::from_path(&path).unwrap();
+    assert_eq!(image.mime_type(), Some("image/svg+xml"));
+    std::fs::remove_file(&path).unwrap();
+
+    let err = Image::from_path(dir.join("dars-missing-image.png")).unwrap_err();
+    assert!(matches!(err, Error::Io(_)));
+}
+
+#[test]
+fn test_deserialize() {
+    let image = Image::from_url("https://example.com/image.png");
+
+    let roundtrip: Image = serde_json::from_value(serde_json::to_value(&image).unwrap()).unwrap();
+    assert_eq!(roundtrip, image);
+
+    let plain: Image = serde_json::from_value(json!("https://example.com/image.png")).unwrap();
+    assert_eq!(plain, image);
+
+    let object: Image =
+        serde_json::from_value(json!({"url": "https://example.com/image.png"})).unwrap();
+    assert_eq!(object, image);
+
+    assert!(serde_json::from_value::<Image>(json!(42)).is_err());
+}
+
+#[Model]
+struct Example {
+    #[field]
+    caption: String,
+
+    #[field]
+    image: Image,
+}
+
+#[test]
+fn test_deserialize_in_model() {
+    let example: Example = serde_json::from_value(json!({
+        "caption": "a cat",
+        "image": "data:image/png;base64,iVBORw0KGgo=",
+    }))
+    .unwrap();
+    assert_eq!(example.image.mime_type(), Some("image/png"));
+}
+
+#[cfg(feature = "image")]
+#[test]
+fn test_downscale() {
+    let mut buf = std::io::Cursor::new(vec![]);
+    image::RgbImage::new(200, 100)
+        .write_to(&mut buf, image::ImageFormat::Png)
+        .unwrap();
+    let original = Image::from_bytes(buf.get_ref()).unwrap();
+
+    let image = original.clone().downscale(50).unwrap();
+    assert_eq!(image.mime_type(), Some("image/png"));
+    let decoded = image::load_from_memory(&image.data().unwrap()).unwrap();
+    assert_eq!((decoded.width(), decoded.height()), (50, 25));
+
+    // Smaller images are unchanged
+    assert_eq!(original.clone().downscale(500).unwrap(), original);
+
+    let err = Image::from_url("https://example.com/image.png")
+        .downscale(50)
+        .unwrap_err();
+    assert!(matches!(err, Error::InvalidArgument(_)));
+}
+
+#[Signature]
+struct Illustrate {
+    #[input]
+    story: String,
+
+    #[output]
+    title: String,
+
+    #[output(desc = "Illustration of the story")]
+    illustration: Image,
+}
+
+#[tokio::test]
+async fn test_image_output() {
+    let generated = Image::from_url("data:image/png;base64,iVBORw0KGgo=");
+    let lm =
+        Arc::new(ScriptedLM::json([json!({"title": "The cat"})]).with_images([generated.clone()]));
+    let predict = Predict::new(lm.clone(), Illustrate::new());
+    let output = predict
+        .call(IllustrateInput {
+            story: "A cat naps in the sun".to_string(),
+        })
+        .await
+        .unwrap();
+    assert_eq!(output.title, "The cat");
+    assert_eq!(output.illustration, generated);
+
+    // The images are requested with the input, the text outputs without the image fields
+    let calls = lm.calls();
+    assert_eq!(calls.len(), 2);
+    let Message::System { instruction } = &calls[0][0] else {
+        panic!("expected a system message");
+    };
+    assert!(instruction.contains("1. `illustration`: Illustration of the story"));
+    assert!(calls[0][1].to_string().contains("A cat naps in the sun"));
+    let Message::System { instruction } = &calls[1][0] else {
+        panic!("expected a system message");
+    };
+    assert!(instruction.contains("`title`"));
+    assert!(!instruction.contains("illustration"));
+}
+
+#[Signature]
+struct Draw {
+    #[input]
+    subject: String,
+
+    #[output]
+    front: Image,
+
+    #[output]
+    back: Image,
+}
+
+#[tokio::test]
+async fn test_image_only_outputs() {
+    let front = Image::from_url("https://example.com/front.png");
+    let back = Image::from_url("https://example.com/back.png");
+    let lm =
+        Arc::new(ScriptedLM::new(Vec::<String>::new()).with_images([front.clone(), back.clone()]));
+    let predict = Predict::new(lm.clone(), Draw::new());
+    let output = predict
+        .call(DrawInput {
+            subject: "a coin".to_string(),
+        })
+        .await
+        .unwrap();
+    assert_eq!((output.front, output.back), (front, back));
+
+    // No text call without text outputs
+    assert_eq!(lm.calls().len(), 1);
+}
+
+struct TextLM;
+
+#[async_trait]
+impl LM for TextLM {
+    async fn call(
+        &self,
+        _messages: Vec<Message>,
+        _schema: Option<Schema>,
+    ) -> Result<String, Error> {
+        Ok(json!({"title": "The cat"}).to_string())
+    }
+}
+
+#[tokio::test]
+async fn test_image_output_unsupported() {
+    let predict = Predict::new(Arc::new(TextLM), Illustrate::new());
+    let err = predict
+        .call(IllustrateInput {
+            story: "A cat naps in the sun".to_string(),
+        })
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::InvalidArgument(msg) if msg.contains("image generation")));
+}
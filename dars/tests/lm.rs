@@ -0,0 +1,108 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use async_trait::async_trait;
+use schemars::Schema;
+
+use da_rs::Error;
+use da_rs::lm::{ErrorClass, FallbackLM, LM, Message, MessageContent, RouterLM, estimate_tokens};
+
+/// LM answering with its name, or failing with the given error.
+struct NamedLM {
+    name: &'static str,
+    error: Option<fn() -> Error>,
+    calls: AtomicUsize,
+}
+
+impl NamedLM {
+    fn ok(name: &'static str) -> Arc<Self> {
+        Arc::new(Self {
+            name,
+            error: None,
+            calls: AtomicUsize::new(0),
+        })
+    }
+
+    fn failing(name: &'static str, error: fn() -> Error) -> Arc<Self> {
+        Arc::new(Self {
+            name,
+            error: Some(error),
+            calls: AtomicUsize::new(0),
+        })
+    }
+}
+
+#[async_trait]
+impl LM for NamedLM {
+    async fn call(&self, _input: Vec<Message>, _schema: Option<Schema>) -> Result<String, Error> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        match self.error {
+            Some(error) => Err(error()),
+            None => Ok(self.name.to_string()),
+        }
+    }
+}
+
+fn messages(text: &str) -> Vec<Message> {
+    vec![Message::User {
+        content: vec![MessageContent::Text {
+            text: text.to_string(),
+        }],
+    }]
+}
+
+#[tokio::test]
+async fn test_fallback_on_unavailable() {
+    let primary = NamedLM::failing("primary", || Error::ModelCall("outage".into()));
+    let secondary = NamedLM::ok("secondary");
+    let lm = FallbackLM::new(vec![primary.clone(), secondary.clone()]);
+
+    assert_eq!(lm.call(messages("hi"), None).await.unwrap(), "secondary");
+    assert_eq!(primary.calls.load(Ordering::SeqCst), 1);
+    assert_eq!(secondary.calls.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn test_fallback_does_not_fail_over_on_other_errors() {
+    let primary = NamedLM::failing("primary", || Error::InvalidArgument("bad".into()));
+    let secondary = NamedLM::ok("secondary");
+    let lm = FallbackLM::new(vec![primary, secondary.clone()]);
+
+    let err = lm.call(messages("hi"), None).await.unwrap_err();
+    assert_eq!(ErrorClass::of(&err), ErrorClass::InvalidRequest);
+    assert_eq!(secondary.calls.load(Ordering::SeqCst), 0);
+}
+
+#[tokio::test]
+async fn test_fallback_configured_classes() {
+    let primary = NamedLM::failing("primary", || Error::InvalidArgument("bad".into()));
+    let secondary = NamedLM::failing("secondary", || Error::ModelCall("outage".into()));
+    let lm = FallbackLM::new(vec![primary, secondary])
+        .with_failover_on([ErrorClass::InvalidRequest, ErrorClass::Unavailable]);
+
+    let err = lm.call(messages("hi"), None).await.unwrap_err();
+    assert!(matches!(err, Error::ModelCall(msg) if msg == "outage"));
+}
+
+#[tokio::test]
+async fn test_router() {
+    let lm = RouterLM::new(NamedLM::ok("mini"))
+        .route(
+            |messages, _| estimate_tokens(messages) > 100,
+            NamedLM::ok("long-context"),
+        )
+        .route(|_, schema| schema.is_none(), NamedLM::ok("text"));
+
+    let schema = Some(schemars::schema_for!(String));
+    assert_eq!(
+        lm.call(messages("short"), schema.clone()).await.unwrap(),
+        "mini"
+    );
+    assert_eq!(
+        lm.call(messages(&"long ".repeat(100)), schema)
+            .await
+            .unwrap(),
+        "long-context"
+    );
+    assert_eq!(lm.call(messages("short"), None).await.unwrap(), "text");
+}
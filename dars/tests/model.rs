@@ -55,3 +55,49 @@ fn test_model_fields() {
         ]
     );
 }
+
+#[test]
+fn test_model_pass_through_attributes() {
+    /// A song on an album.
+    #[Model]
+    #[serde(deny_unknown_fields)]
+    struct Song {
+        /// Title of the song.
+        #[serde(rename = "name")]
+        title: String,
+
+        #[field(desc = "Length in seconds")]
+        #[schemars(range(min = 1))]
+        r#length: u32,
+    }
+
+    assert_eq!(
+        Song::fields(),
+        &[
+            Field {
+                name: "name",
+                description: Some("Title of the song.")
+            },
+            Field {
+                name: "length",
+                description: Some("Length in seconds")
+            }
+        ]
+    );
+
+    let schema = schemars::schema_for!(Song);
+    assert_eq!(schema.get("description").unwrap(), "A song on an album.");
+    assert_eq!(
+        schema.get("properties").unwrap()["name"]["description"],
+        "Title of the song."
+    );
+    assert_eq!(schema.get("properties").unwrap()["length"]["minimum"], 1);
+
+    let song: Song =
+        serde_json::from_value(serde_json::json!({"name": "Song", "length": 60})).unwrap();
+    assert_eq!(song.title, "Song");
+    assert!(
+        serde_json::from_value::<Song>(serde_json::json!({"name": "Song", "length": 60, "x": 1}))
+            .is_err()
+    );
+}
@@ -0,0 +1,98 @@
+use std::sync::Arc;
+
+use serde_json::json;
+
+use da_rs::lm::{Message, MessageContent};
+use da_rs::testing::ScriptedLM;
+use da_rs::*;
+
+#[Signature]
+struct Answer {
+    #[input]
+    question: String,
+
+    #[output]
+    answer: String,
+
+    #[output]
+    note: Option<String>,
+
+    #[output(default = 0.5)]
+    confidence: f32,
+
+    #[output(desc = "Where the answer comes from", default = "unknown")]
+    source: String,
+}
+
+fn input() -> AnswerInput {
+    AnswerInput {
+        question: "What is 2 + 2?".to_string(),
+    }
+}
+
+#[tokio::test]
+async fn test_omitted_fields() {
+    let lm = Arc::new(ScriptedLM::json([json!({"answer": "4"})]));
+    let predict = Predict::new(lm.clone(), Answer::new());
+    let output = predict.call(input()).await.unwrap();
+    assert_eq!(output.answer, "4");
+    assert_eq!(output.note, None);
+    assert_eq!(output.confidence, 0.5);
+    assert_eq!(output.source, "unknown");
+
+    let Message::System { instruction } = &lm.calls()[0][0] else {
+        panic!("expected a system message");
+    };
+    assert!(instruction.contains("1. `answer` (string): \n"));
+    assert!(instruction.contains("2. `note` (string | null, optional): \n"));
+    assert!(instruction.contains("3. `confidence` (number<float>, default: 0.5): \n"));
+    assert!(
+        instruction
+            .contains("4. `source` (string, default: \"unknown\"): Where the answer comes from\n")
+    );
+}
+
+#[tokio::test]
+async fn test_missing_fields() {
+    let lm = Arc::new(ScriptedLM::json([json!({"note": "easy"})]));
+    let predict = Predict::new(lm, Answer::new()).with_max_retries(0);
+    let err = predict.call(input()).await.unwrap_err();
+    assert!(matches!(&err, Error::MissingFields(fields) if fields == &["answer"]));
+    assert_eq!(err.to_string(), "missing output fields: answer");
+}
+
+#[tokio::test]
+async fn test_missing_fields_retry() {
+    let lm = Arc::new(ScriptedLM::json([
+        json!({"note": "easy"}),
+        json!({"answer": "4"}),
+    ]));
+    let predict = Predict::new(lm.clone(), Answer::new()).with_max_retries(1);
+    let output = predict.call(input()).await.unwrap();
+    assert_eq!(output.answer, "4");
+
+    let calls = lm.calls();
+    let Some(Message::User { content }) = calls[1].last() else {
+        panic!("expected feedback");
+    };
+    let [MessageContent::Text { text }] = content.as_slice() else {
+        panic!("expected a text part");
+    };
+    assert!(text.contains("missing required output field `answer`"));
+}
+
+#[test]
+fn test_model_default() {
+    #[Model]
+    struct Settings {
+        #[field(default = 3)]
+        retries: u32,
+
+        #[field(default = vec!["a".to_string()])]
+        tags: Vec<String>,
+    }
+
+    let settings: Settings = serde_json::from_value(json!({})).unwrap();
+    assert_eq!(settings.retries, 3);
+    assert_eq!(settings.tags, ["a"]);
+}
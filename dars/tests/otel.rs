@@ -0,0 +1,241 @@
+#![cfg(feature = "otel")]
+
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use serde_json::json;
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Metadata, Subscriber};
+
+use da_rs::testing::ScriptedLM;
+use da_rs::*;
+
+#[derive(Debug, Clone)]
+struct SpanData {
+    name: &'static str,
+    parent: Option<u64>,
+    fields: HashMap<&'static str, String>,
+}
+
+impl Visit for SpanData {
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        self.fields.insert(field.name(), format!("{value:?}"));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.fields.insert(field.name(), value.to_string());
+    }
+}
+
+/// Subscriber recording the spans and their fields.
+#[derive(Clone, Default)]
+struct Recorder {
+    spans: Arc<Mutex<Vec<SpanData>>>,
+    stack: Arc<Mutex<Vec<u64>>>,
+}
+
+impl Recorder {
+    fn spans(&self) -> Vec<SpanData> {
+        self.spans.lock().unwrap().clone()
+    }
+}
+
+impl Subscriber for Recorder {
+    fn enabled(&self, _metadata: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, attrs: &Attributes<'_>) -> Id {
+        let parent = match attrs.parent() {
+            Some(parent) => Some(parent.into_u64()),
+            None if attrs.is_contextual() => self.stack.lock().unwrap().last().copied(),
+            None => None,
+        };
+        let mut span = SpanData {
+            name: attrs.metadata().name(),
+            parent,
+            fields: HashMap::new(),
+        };
+        attrs.record(&mut span);
+
+        let mut spans = self.spans.lock().unwrap();
+        spans.push(span);
+        Id::from_u64(spans.len() as u64)
+    }
+
+    fn record(&self, id: &Id, values: &Record<'_>) {
+        let mut spans = self.spans.lock().unwrap();
+        values.record(&mut spans[id.into_u64() as usize - 1]);
+    }
+
+    fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+    fn event(&self, _event: &Event<'_>) {}
+
+    fn enter(&self, id: &Id) {
+        self.stack.lock().unwrap().push(id.into_u64());
+    }
+
+    fn exit(&self, _id: &Id) {
+        self.stack.lock().unwrap().pop();
+    }
+}
+
+#[Signature]
+struct Sig {
+    #[input]
+    question: String,
+
+    #[output]
+    answer: String,
+}
+
+#[Signature]
+struct Transcribe {
+    #[input]
+    recording: Audio,
+
+    #[output]
+    transcript: String,
+}
+
+fn input(question: &str) -> SigInput {
+    SigInput {
+        question: question.to_string(),
+    }
+}
+
+struct Pipeline {
+    predict: Predict<Sig>,
+}
+
+#[async_trait]
+impl Module for Pipeline {
+    type Input = SigInput;
+    type Output = SigOutput;
+
+    async fn call(&self, input: SigInput) -> Result<SigOutput, Error> {
+        traced("Pipeline", input, |input| self.predict.call(input)).await
+    }
+}
+
+#[tokio::test]
+async fn test_spans() {
+    let recorder = Recorder::default();
+    let _guard = tracing::subscriber::set_default(recorder.clone());
+
+    let lm = Arc::new(ScriptedLM::json([
+        json!({"answer": "first answer"}),
+        json!({"answer": "second answer"}),
+    ]));
+    let pipeline = Pipeline {
+        predict: Predict::new(lm, Sig::new()),
+    };
+
+    // Content is not captured by default
+    otel::set_capture_content(false);
+    pipeline.call(input("first question")).await.unwrap();
+    otel::set_capture_content(true);
+    pipeline.call(input("second question")).await.unwrap();
+
+    let spans = recorder.spans();
+    assert_eq!(spans.len(), 6);
+    for call in spans.chunks(3) {
+        let [module, predict, lm] = call else {
+            unreachable!()
+        };
+        assert_eq!(module.name, "dars.module");
+        assert_eq!(module.fields["otel.name"], "Pipeline");
+        assert_eq!(module.parent, None);
+
+        assert_eq!(predict.name, "dars.module");
+        assert_eq!(predict.fields["dars.module.name"], "Predict<Sig>");
+
+        assert_eq!(lm.name, "gen_ai.chat");
+        assert_eq!(lm.fields["otel.kind"], "client");
+        assert_eq!(lm.fields["gen_ai.operation.name"], "chat");
+        assert!(!lm.fields.contains_key("error.type"));
+    }
+
+    assert_eq!(spans[1].parent, Some(1));
+    assert_eq!(spans[2].parent, Some(2));
+    assert_eq!(spans[4].parent, Some(4));
+    assert_eq!(spans[5].parent, Some(5));
+
+    assert!(!spans[2].fields.contains_key("gen_ai.input.messages"));
+    assert!(!spans[2].fields.contains_key("gen_ai.output.messages"));
+    assert!(spans[5].fields["gen_ai.input.messages"].contains("second question"));
+    let output: serde_json::Value =
+        serde_json::from_str(&spans[5].fields["gen_ai.output.messages"]).unwrap();
+    assert_eq!(output[0]["role"], "assistant");
+    assert_eq!(
+        output[0]["parts"][0]["content"],
+        json!({"answer": "second answer"}).to_string()
+    );
+
+    // Media parts are captured with their MIME type
+    let lm = Arc::new(ScriptedLM::json([json!({"transcript": "hello"})]));
+    let audio = Audio::from_bytes(b"ID3\x04\x00\x00\x00\x00\x00\x00").unwrap();
+    Predict::new(lm, Transcribe::new())
+        .call(TranscribeInput { recording: audio })
+        .await
+        .unwrap();
+    let spans = recorder.spans();
+    let input: serde_json::Value =
+        serde_json::from_str(&spans[7].fields["gen_ai.input.messages"]).unwrap();
+    assert_eq!(input[1]["parts"][1]["modality"], "audio");
+    assert_eq!(input[1]["parts"][1]["mime_type"], "audio/mpeg");
+}
+
+#[tokio::test]
+async fn test_error_spans() {
+    let recorder = Recorder::default();
+    let _guard = tracing::subscriber::set_default(recorder.clone());
+
+    let predict = Predict::new(Arc::new(ScriptedLM::new(Vec::<String>::new())), Sig::new());
+    predict.call(input("question")).await.unwrap_err();
+
+    let spans = recorder.spans();
+    assert_eq!(spans.len(), 2);
+    for span in spans {
+        assert_eq!(span.fields["otel.status_code"], "ERROR");
+        assert_eq!(span.fields["error.type"], "unavailable");
+    }
+}
+
+#[tokio::test]
+async fn test_spans_only_for_module_calls() {
+    use da_rs::lm::{FallbackLM, LM, Message, MessageContent};
+
+    let recorder = Recorder::default();
+    let _guard = tracing::subscriber::set_default(recorder.clone());
+
+    // Direct LM calls emit no span
+    let lm = ScriptedLM::json([json!({"answer": "direct"})]);
+    let messages = vec![Message::User {
+        content: vec![MessageContent::Text {
+            text: "hi".to_string(),
+        }],
+    }];
+    lm.call(messages, None).await.unwrap();
+    assert!(recorder.spans().is_empty());
+
+    // The failover of a wrapper LM is a single LM span
+    let lm = Arc::new(FallbackLM::new(vec![
+        Arc::new(ScriptedLM::new(Vec::<String>::new())),
+        Arc::new(ScriptedLM::json([json!({"answer": "fallback"})])),
+    ]));
+    let output = Predict::new(lm, Sig::new())
+        .call(input("question"))
+        .await
+        .unwrap();
+    assert_eq!(output.answer, "fallback");
+
+    let spans = recorder.spans();
+    let names = spans.iter().map(|s| s.name).collect::<Vec<_>>();
+    assert_eq!(names, ["dars.module", "gen_ai.chat"]);
+    assert!(!spans[1].fields.contains_key("error.type"));
+}
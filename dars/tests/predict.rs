@@ -5,6 +5,7 @@ use schemars::Schema;
 use serde_json::json;
 
 use da_rs::lm::{LM, Message};
+use da_rs::testing::ScriptedLM;
 use da_rs::*;
 
 struct FixedLM {
@@ -71,3 +72,143 @@ async fn test_predict_with_invalid_output() {
 
     assert!(matches!(output, Error::SerdeJson(_)));
 }
+
+#[Signature]
+struct ConstrainedSig {
+    #[input]
+    question: String,
+
+    #[output(length(min = 1))]
+    answer: String,
+
+    #[output(range(min = 0, max = 1))]
+    confidence: f32,
+}
+
+#[tokio::test]
+async fn test_predict_with_constraint_violation() {
+    let lm = Arc::new(FixedLM::new(json!({
+        "answer": "",
+        "confidence": 1.5
+    })));
+
+    let predict = Predict::new(lm, ConstrainedSig::new());
+    let err = predict
+        .call(ConstrainedSigInput {
+            question: "input value".to_string(),
+        })
+        .await
+        .expect_err("should error");
+
+    let Error::Validation(violations) = err else {
+        panic!("expected validation error, got {err:?}");
+    };
+    assert_eq!(
+        violations,
+        vec![
+            Violation {
+                path: "answer".to_string(),
+                message: "must have at least 1 characters".to_string()
+            },
+            Violation {
+                path: "confidence".to_string(),
+                message: "must be <= 1".to_string()
+            }
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_predict_retries_only_when_requested() {
+    let invalid = json!({"answer": "", "confidence": 0.5});
+    let valid = json!({"answer": "yes", "confidence": 0.5});
+    let input = || ConstrainedSigInput {
+        question: "input value".to_string(),
+    };
+
+    // A single LM call without checks
+    let lm = Arc::new(ScriptedLM::json([invalid.clone(), valid.clone()]));
+    let err = Predict::new(lm.clone(), ConstrainedSig::new())
+        .call(input())
+        .await
+        .expect_err("should error");
+    assert!(matches!(err, Error::Validation(_)));
+    assert_eq!(lm.calls().len(), 1);
+
+    // Retried with feedback when requested
+    let lm = Arc::new(ScriptedLM::json([invalid, valid]));
+    let output = Predict::new(lm.clone(), ConstrainedSig::new())
+        .with_max_retries(1)
+        .call(input())
+        .await
+        .unwrap();
+    assert_eq!(output.answer, "yes");
+    assert_eq!(lm.calls().len(), 2);
+}
+
+#[tokio::test]
+async fn test_predict_assert_retries_with_feedback() {
+    let lm = Arc::new(ScriptedLM::json([
+        json!({"answer": "way too long answer", "confidence": 0.5}),
+        json!({"answer": "short", "confidence": 0.5}),
+    ]));
+
+    let predict = Predict::new(lm.clone(), Sig::new()).assert(
+        |o: &SigOutput| o.answer.split_whitespace().count() <= 2,
+        "answer must be at most 2 words",
+    );
+    let output = predict
+        .call(SigInput {
+            question: "input value".to_string(),
+        })
+        .await
+        .unwrap();
+    assert_eq!(output.answer, "short");
+
+    let calls = lm.calls();
+    assert_eq!(calls.len(), 2);
+    assert_eq!(calls[0].len(), 2);
+    assert_eq!(calls[1].len(), 4);
+    assert!(matches!(&calls[1][2], Message::Assistant { .. }));
+    assert!(
+        calls[1][3]
+            .to_string()
+            .contains("- answer must be at most 2 words")
+    );
+}
+
+#[tokio::test]
+async fn test_predict_assert_exhausted() {
+    let resp = json!({"answer": "way too long answer", "confidence": 0.5});
+    let lm = Arc::new(ScriptedLM::json([resp.clone(), resp]));
+
+    let predict = Predict::new(lm, Sig::new())
+        .assert(|o: &SigOutput| o.answer.len() < 5, "answer too long")
+        .with_max_retries(1);
+    let err = predict
+        .call(SigInput {
+            question: "input value".to_string(),
+        })
+        .await
+        .expect_err("should error");
+    assert!(matches!(err, Error::Assertion(msg) if msg == "answer too long"));
+}
+
+#[tokio::test]
+async fn test_predict_suggest_exhausted() {
+    let lm = Arc::new(ScriptedLM::json([json!({
+        "answer": "way too long answer",
+        "confidence": 0.5
+    })]));
+
+    let predict = Predict::new(lm, Sig::new())
+        .suggest(|o: &SigOutput| o.answer.len() < 5, "answer too long")
+        .with_max_retries(0);
+    let output = predict
+        .call(SigInput {
+            question: "input value".to_string(),
+        })
+        .await
+        .unwrap();
+    assert_eq!(output.answer, "way too long answer");
+}
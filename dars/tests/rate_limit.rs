@@ -0,0 +1,141 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use schemars::Schema;
+use tokio::time::Instant;
+
+use da_rs::Error;
+use da_rs::lm::{LM, Message, MessageContent, RateLimitedLM, RateLimits};
+
+/// LM taking 10ms per call, failing the first `rate_limited` calls with a rate limit error.
+struct SlowLM {
+    calls: AtomicUsize,
+    in_flight: AtomicUsize,
+    max_in_flight: AtomicUsize,
+    rate_limited: usize,
+}
+
+impl SlowLM {
+    fn new(rate_limited: usize) -> Self {
+        Self {
+            calls: AtomicUsize::new(0),
+            in_flight: AtomicUsize::new(0),
+            max_in_flight: AtomicUsize::new(0),
+            rate_limited,
+        }
+    }
+}
+
+#[async_trait]
+impl LM for SlowLM {
+    async fn call(&self, _input: Vec<Message>, _schema: Option<Schema>) -> Result<String, Error> {
+        let call = self.calls.fetch_add(1, Ordering::SeqCst);
+        let n = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+        self.max_in_flight.fetch_max(n, Ordering::SeqCst);
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        self.in_flight.fetch_sub(1, Ordering::SeqCst);
+
+        if call < self.rate_limited {
+            return Err(rate_limit_error());
+        }
+        Ok("ok".to_string())
+    }
+}
+
+#[cfg(feature = "openai")]
+fn rate_limit_error() -> Error {
+    use async_openai::error::{ApiError, OpenAIError};
+
+    Error::OpenAI(OpenAIError::ApiError(ApiError {
+        message: "Rate limit reached for requests. Please try again in 2s.".to_string(),
+        r#type: Some("requests".to_string()),
+        param: None,
+        code: Some("rate_limit_exceeded".to_string()),
+    }))
+}
+
+#[cfg(not(feature = "openai"))]
+fn rate_limit_error() -> Error {
+    Error::ModelCall("rate limited".to_string())
+}
+
+fn messages(text: &str) -> Vec<Message> {
+    vec![Message::User {
+        content: vec![MessageContent::Text {
+            text: text.to_string(),
+        }],
+    }]
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_requests_per_minute() {
+    let lm = RateLimitedLM::new(
+        Arc::new(SlowLM::new(0)),
+        RateLimits::default().with_requests_per_minute(2),
+    );
+
+    let start = Instant::now();
+    for _ in 0..3 {
+        lm.call(messages("hi"), None).await.unwrap();
+    }
+
+    // The bucket starts full and refills one request every 30 seconds
+    let elapsed = start.elapsed();
+    assert!(elapsed >= Duration::from_secs(30), "{elapsed:?}");
+    assert!(elapsed < Duration::from_secs(31), "{elapsed:?}");
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_tokens_per_minute() {
+    let lm = RateLimitedLM::new(
+        Arc::new(SlowLM::new(0)),
+        RateLimits::default().with_tokens_per_minute(600),
+    );
+
+    // ~500 tokens leave 100 in the bucket, the second call waits for the 200 missing ones
+    let start = Instant::now();
+    lm.call(messages(&"a".repeat(2000)), None).await.unwrap();
+    lm.call(messages(&"a".repeat(1200)), None).await.unwrap();
+
+    let elapsed = start.elapsed();
+    assert!(elapsed >= Duration::from_secs(20), "{elapsed:?}");
+    assert!(elapsed < Duration::from_secs(21), "{elapsed:?}");
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_max_in_flight() {
+    let inner = Arc::new(SlowLM::new(0));
+    let lm = RateLimitedLM::new(inner.clone(), RateLimits::default().with_max_in_flight(2));
+
+    let calls = (0..5).map(|_| lm.call(messages("hi"), None));
+    for result in futures::future::join_all(calls).await {
+        result.unwrap();
+    }
+
+    assert_eq!(inner.max_in_flight.load(Ordering::SeqCst), 2);
+}
+
+#[cfg(feature = "openai")]
+#[tokio::test(start_paused = true)]
+async fn test_retry_after_rate_limit() {
+    let inner = Arc::new(SlowLM::new(1));
+    let lm = RateLimitedLM::new(inner.clone(), RateLimits::default());
+
+    let start = Instant::now();
+    assert_eq!(lm.call(messages("hi"), None).await.unwrap(), "ok");
+    assert_eq!(inner.calls.load(Ordering::SeqCst), 2);
+    assert!(start.elapsed() >= Duration::from_secs(2));
+}
+
+#[cfg(feature = "openai")]
+#[tokio::test(start_paused = true)]
+async fn test_retries_exhausted() {
+    let inner = Arc::new(SlowLM::new(5));
+    let lm = RateLimitedLM::new(inner.clone(), RateLimits::default().with_max_retries(1));
+
+    let err = lm.call(messages("hi"), None).await.unwrap_err();
+    assert!(da_rs::lm::is_rate_limit(&err));
+    assert_eq!(inner.calls.load(Ordering::SeqCst), 2);
+}
@@ -0,0 +1,127 @@
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use schemars::Schema;
+
+use da_rs::lm::{LM, Message};
+use da_rs::*;
+
+/// LM answering with the number of the call and recording the call context.
+struct CountingLM {
+    calls: Mutex<Vec<(CallContext, Vec<Message>)>>,
+}
+
+impl CountingLM {
+    fn new() -> Self {
+        Self {
+            calls: Mutex::new(vec![]),
+        }
+    }
+}
+
+#[async_trait]
+impl LM for CountingLM {
+    async fn call(&self, input: Vec<Message>, _schema: Option<Schema>) -> Result<String, Error> {
+        let mut calls = self.calls.lock().unwrap();
+        calls.push((CallContext::current(), input));
+        Ok(format!("{{\"value\": {}}}", calls.len()))
+    }
+}
+
+#[Signature]
+struct Sig {
+    #[input]
+    question: String,
+
+    #[output]
+    value: i32,
+}
+
+fn input() -> SigInput {
+    SigInput {
+        question: "question".to_string(),
+    }
+}
+
+#[tokio::test]
+async fn test_best_of_n_returns_best_output() {
+    let lm = Arc::new(CountingLM::new());
+    let module = BestOfN::new(
+        Predict::new(lm.clone(), Sig::new()),
+        3,
+        |_, o: &SigOutput| -(o.value as f64 - 2.0).abs(),
+    );
+
+    let output = module.call(input()).await.unwrap();
+    assert_eq!(output.value, 2);
+
+    let calls = lm.calls.lock().unwrap();
+    let contexts = calls.iter().map(|(ctx, _)| ctx.clone()).collect::<Vec<_>>();
+    assert_eq!(
+        contexts,
+        vec![
+            CallContext::default().with_rollout_id(0),
+            CallContext::default()
+                .with_rollout_id(1)
+                .with_temperature(1.0),
+            CallContext::default()
+                .with_rollout_id(2)
+                .with_temperature(1.0),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_best_of_n_stops_at_threshold() {
+    let lm = Arc::new(CountingLM::new());
+    let module = BestOfN::new(
+        Predict::new(lm.clone(), Sig::new()),
+        5,
+        |_, o: &SigOutput| o.value as f64,
+    )
+    .with_threshold(2.0);
+
+    let output = module.call(input()).await.unwrap();
+    assert_eq!(output.value, 2);
+    assert_eq!(lm.calls.lock().unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn test_refine_feeds_back_failed_attempts() {
+    let lm = Arc::new(CountingLM::new());
+    let module = Refine::new(
+        Predict::new(lm.clone(), Sig::new()),
+        5,
+        |_, o: &SigOutput| o.value as f64,
+        3.0,
+    )
+    .with_feedback(|_, o, reward| format!("value {} scored {reward}", o.value));
+
+    let output = module.call(input()).await.unwrap();
+    assert_eq!(output.value, 3);
+
+    let calls = lm.calls.lock().unwrap();
+    assert_eq!(calls.len(), 3);
+    assert_eq!(calls[0].0.feedback, None);
+    assert_eq!(calls[1].0.feedback.as_deref(), Some("value 1 scored 1"));
+    assert_eq!(calls[2].0.feedback.as_deref(), Some("value 2 scored 2"));
+    assert_eq!(
+        calls[2].1.last().unwrap().to_string(),
+        "User:\nvalue 2 scored 2\n"
+    );
+}
+
+#[tokio::test]
+async fn test_refine_returns_best_below_threshold() {
+    let lm = Arc::new(CountingLM::new());
+    let module = Refine::new(
+        Predict::new(lm.clone(), Sig::new()),
+        2,
+        |_, o: &SigOutput| o.value as f64,
+        10.0,
+    );
+
+    let output = module.call(input()).await.unwrap();
+    assert_eq!(output.value, 2);
+    assert_eq!(CallContext::current(), CallContext::default());
+}
@@ -115,3 +115,412 @@ fn test_signature_generates_input_output_models() {
         ]
     );
 }
+
+#[Signature]
+struct ConstrainedSig {
+    #[input]
+    text: String,
+
+    #[output(desc = "Confidence score", range(min = 0, max = 1))]
+    confidence: f32,
+
+    #[output(length(max = 3))]
+    tags: Vec<String>,
+
+    #[output(regex = "^[A-Z]{3}$")]
+    code: String,
+
+    #[output(one_of = ["positive", "negative"])]
+    sentiment: String,
+}
+
+#[test]
+fn test_signature_constraints_in_output_schema() {
+    let schema = schema_for!(ConstrainedSigOutput);
+    let properties = schema.get("properties").unwrap();
+    assert_eq!(properties["confidence"]["minimum"], 0);
+    assert_eq!(properties["confidence"]["maximum"], 1);
+    assert_eq!(properties["tags"]["maxItems"], 3);
+    assert_eq!(properties["code"]["pattern"], "^[A-Z]{3}$");
+    assert_eq!(
+        properties["sentiment"]["enum"],
+        serde_json::json!(["positive", "negative"])
+    );
+    assert_eq!(
+        ConstrainedSigOutput::fields()[0],
+        Field {
+            name: "confidence",
+            description: Some("Confidence score")
+        }
+    );
+}
+
+trait Label {}
+
+#[Model]
+#[serde(rename_all = "lowercase")]
+enum Sentiment {
+    Positive,
+    Negative,
+}
+
+impl Label for Sentiment {}
+
+#[Signature("Classify the text.")]
+struct Classify<L: Label> {
+    #[input]
+    text: String,
+
+    #[output]
+    label: L,
+
+    #[output]
+    alternatives: Vec<L>,
+}
+
+#[Signature]
+struct Summarize<D>
+where
+    D: Label,
+{
+    #[input]
+    document: D,
+
+    #[output]
+    summary: String,
+}
+
+#[tokio::test]
+async fn test_generic_signature() {
+    use std::sync::Arc;
+
+    use da_rs::{Module, Predict, testing::ScriptedLM};
+    use serde_json::json;
+
+    let sig = Classify::<Sentiment>::new();
+    assert_eq!(sig.instruction(), "Classify the text.");
+    assert_eq!(
+        sig.field("label").unwrap().as_value()["enum"],
+        json!(["positive", "negative"])
+    );
+
+    let lm = Arc::new(ScriptedLM::json([
+        json!({"label": "positive", "alternatives": ["negative"]}),
+    ]));
+    let output: ClassifyOutput<Sentiment> = Predict::new(lm, sig)
+        .call(ClassifyInput {
+            text: "Great!".to_string(),
+        })
+        .await
+        .unwrap();
+    assert_eq!(output.label, Sentiment::Positive);
+    assert_eq!(output.alternatives, [Sentiment::Negative]);
+
+    // Type parameters are only added to the models that use them
+    let input = SummarizeInput {
+        document: Sentiment::Negative,
+    };
+    let output = SummarizeOutput {
+        summary: "Negative".to_string(),
+    };
+    assert_eq!(input.document, Sentiment::Negative);
+    assert_eq!(output.summary, "Negative");
+    assert_eq!(
+        Summarize::<Sentiment>::new().input_fields()[0].name,
+        "document"
+    );
+}
+
+#[Signature]
+struct Lookup {
+    #[input]
+    r#type: String,
+
+    #[output]
+    #[serde(rename = "result")]
+    value: String,
+}
+
+#[test]
+fn test_signature_field_names() {
+    let sig = Lookup::new();
+    assert_eq!(sig.input_fields()[0].name, "type");
+    assert_eq!(sig.output_fields()[0].name, "result");
+    assert!(sig.field("type").is_some());
+    assert!(sig.field("result").is_some());
+
+    let output: LookupOutput = serde_json::from_value(serde_json::json!({"result": "x"})).unwrap();
+    assert_eq!(output.value, "x");
+}
+
+/// Extract the customer details.
+///
+/// Use the names as written in the email.
+#[Signature]
+struct ExtractCustomer {
+    /// The email from the customer
+    #[input]
+    email: String,
+
+    /// The customer's name
+    #[output]
+    name: String,
+
+    /// Overridden by the description
+    #[output(desc = "The customer's city")]
+    city: String,
+}
+
+/// Overridden by the instruction.
+#[Signature("Summarize the text.")]
+struct DocSummarize {
+    #[input]
+    text: String,
+}
+
+#[test]
+fn test_signature_doc_comments() {
+    let sig = ExtractCustomer::new();
+    assert_eq!(
+        sig.instruction(),
+        "Extract the customer details.\nUse the names as written in the email."
+    );
+    assert_eq!(
+        sig.input_fields(),
+        &[Field {
+            name: "email",
+            description: Some("The email from the customer")
+        }]
+    );
+    assert_eq!(
+        sig.output_fields(),
+        &[
+            Field {
+                name: "name",
+                description: Some("The customer's name")
+            },
+            Field {
+                name: "city",
+                description: Some("The customer's city")
+            }
+        ]
+    );
+    let schema = schema_for!(ExtractCustomerOutput);
+    let properties = schema.get("properties").unwrap();
+    assert_eq!(properties["name"]["description"], "The customer's name");
+    assert_eq!(properties["city"]["description"], "The customer's city");
+
+    assert_eq!(DocSummarize::new().instruction(), "Summarize the text.");
+}
+
+/// A support ticket.
+#[Model]
+struct Ticket {
+    /// Subject of the ticket
+    subject: String,
+
+    #[field]
+    customer: Customer,
+}
+
+#[Model]
+struct Customer {
+    #[field]
+    name: String,
+
+    #[field]
+    plan: Option<String>,
+}
+
+#[Model]
+#[serde(rename_all = "lowercase")]
+enum Priority {
+    Low,
+    High,
+}
+
+#[Model]
+struct Triage {
+    #[field(desc = "Priority of the ticket")]
+    priority: Priority,
+
+    #[field]
+    team: String,
+}
+
+/// Triage the support ticket.
+#[Signature(input = Ticket, output = Triage)]
+struct TriageTicket;
+
+#[tokio::test]
+async fn test_signature_on_models() {
+    use std::sync::Arc;
+
+    use da_rs::{Module, Predict, lm::Message, testing::ScriptedLM};
+    use serde_json::json;
+
+    let sig = TriageTicket::new();
+    assert_eq!(sig.instruction(), "Triage the support ticket.");
+    assert_eq!(sig.input_fields(), Ticket::fields());
+    assert_eq!(sig.output_fields(), Triage::fields());
+    assert_eq!(
+        sig.field("priority").unwrap().as_value()["enum"],
+        serde_json::json!(["low", "high"])
+    );
+
+    let lm = Arc::new(ScriptedLM::json([
+        json!({"priority": "high", "team": "billing"}),
+    ]));
+    let output = Predict::new(lm.clone(), sig)
+        .call(Ticket {
+            subject: "Charged twice".to_string(),
+            customer: Customer {
+                name: "Ada".to_string(),
+                plan: None,
+            },
+        })
+        .await
+        .unwrap();
+    assert_eq!(output.priority, Priority::High);
+    assert_eq!(output.team, "billing");
+
+    let Message::System { instruction } = &lm.calls()[0][0] else {
+        panic!("expected a system message");
+    };
+    assert!(
+        instruction.contains("2. `customer` (Customer {name: string, plan: string | null}): \n")
+    );
+    assert!(
+        instruction.contains("1. `priority` (Literal['low', 'high']): Priority of the ticket\n")
+    );
+}
+
+#[Model]
+struct Sketch {
+    #[field]
+    request: String,
+
+    #[field]
+    history: da_rs::History,
+}
+
+#[Model]
+struct Drawing {
+    #[field]
+    caption: String,
+
+    #[field(desc = "The drawing")]
+    drawing: da_rs::Image,
+}
+
+/// Draw the request.
+#[Signature(input = Sketch, output = Drawing)]
+struct DrawSketch;
+
+#[tokio::test]
+async fn test_signature_on_models_with_media() {
+    use std::sync::Arc;
+
+    use da_rs::{History, Image, Module, Predict, testing::ScriptedLM};
+    use serde_json::json;
+
+    let sig = DrawSketch::new();
+    assert!(History::is_history(sig.field("history").unwrap()));
+    assert!(Image::is_image(sig.field("drawing").unwrap()));
+    assert!(!Image::is_image(sig.field("caption").unwrap()));
+    assert_eq!(
+        sig.field("drawing").unwrap().as_value()["description"],
+        "The drawing"
+    );
+
+    let generated = Image::from_url("data:image/png;base64,iVBORw0KGgo=");
+    let lm =
+        Arc::new(ScriptedLM::json([json!({"caption": "A cat"})]).with_images([generated.clone()]));
+    let history = History::new()
+        .with_turn(&json!({"request": "A dog"}), &json!({"caption": "A dog"}))
+        .unwrap();
+    let output = Predict::new(lm.clone(), sig)
+        .call(Sketch {
+            request: "A cat".to_string(),
+            history,
+        })
+        .await
+        .unwrap();
+    assert_eq!(output.caption, "A cat");
+    assert_eq!(output.drawing, generated);
+
+    // The history is expanded into turns instead of an input field
+    let calls = lm.calls();
+    assert_eq!(calls.len(), 2);
+    assert_eq!(calls[1].len(), 4);
+    assert!(!calls[1][3].to_string().contains("history"));
+}
+
+#[Model]
+struct Draft {
+    #[field]
+    text: String,
+}
+
+#[Model]
+struct Edit {
+    #[field]
+    text: String,
+}
+
+#[Signature(input = Draft, output = Edit)]
+struct EditDraft;
+
+#[test]
+#[should_panic(expected = "Field `text` is declared by both the input and output models")]
+fn test_signature_on_models_with_shared_field() {
+    EditDraft::new();
+}
+
+#[Model]
+#[serde(rename_all = "camelCase")]
+struct Order {
+    #[field]
+    customer_name: String,
+
+    #[field]
+    #[serde(rename = "sku")]
+    product_code: String,
+}
+
+#[Model]
+#[serde(rename_all = "SCREAMING-KEBAB-CASE")]
+struct Shipment {
+    #[field]
+    carrier_name: String,
+}
+
+#[Signature(input = Order, output = Shipment)]
+struct ShipOrder;
+
+#[tokio::test]
+async fn test_signature_on_renamed_models() {
+    use std::sync::Arc;
+
+    use da_rs::{Module, Predict, testing::ScriptedLM};
+    use serde_json::json;
+
+    let names = |fields: &[Field]| fields.iter().map(|f| f.name).collect::<Vec<_>>();
+    assert_eq!(names(Order::fields()), ["customerName", "sku"]);
+    assert_eq!(names(Shipment::fields()), ["CARRIER-NAME"]);
+
+    let lm = Arc::new(ScriptedLM::json([json!({"CARRIER-NAME": "Post"})]));
+    let output = Predict::new(lm.clone(), ShipOrder::new())
+        .call(Order {
+            customer_name: "Ada".to_string(),
+            product_code: "A1".to_string(),
+        })
+        .await
+        .unwrap();
+    assert_eq!(output.carrier_name, "Post");
+    assert!(
+        lm.calls()[0][1]
+            .to_string()
+            .contains("[[ ## customerName ## ]]")
+    );
+}
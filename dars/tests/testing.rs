@@ -0,0 +1,211 @@
+use std::sync::Arc;
+
+use serde_json::json;
+
+use da_rs::lm::{LM, Message, MessageContent};
+use da_rs::testing::{MatchMode, RecordingLM, ReplayLM, ScriptedLM};
+use da_rs::*;
+
+#[Signature]
+struct Sig {
+    #[input]
+    question: String,
+
+    #[output]
+    answer: String,
+}
+
+#[Signature]
+struct Illustrate {
+    #[input]
+    story: String,
+
+    #[output]
+    title: String,
+
+    #[output]
+    illustration: Image,
+}
+
+fn input(question: &str) -> SigInput {
+    SigInput {
+        question: question.to_string(),
+    }
+}
+
+fn fixture(name: &str) -> std::path::PathBuf {
+    std::env::temp_dir().join(format!("dars-{}-{name}.json", std::process::id()))
+}
+
+#[tokio::test]
+async fn test_scripted_lm() {
+    let lm = ScriptedLM::new(["first", "second"]);
+    let messages = vec![Message::User {
+        content: vec![MessageContent::Text {
+            text: "hi".to_string(),
+        }],
+    }];
+
+    assert_eq!(lm.call(messages.clone(), None).await.unwrap(), "first");
+    assert_eq!(lm.call(messages.clone(), None).await.unwrap(), "second");
+    assert_eq!(lm.remaining(), 0);
+    assert_eq!(lm.calls(), vec![messages.clone(), messages.clone()]);
+
+    let err = lm.call(messages, None).await.unwrap_err();
+    assert!(matches!(err, Error::ModelCall(msg) if msg.contains("no response left")));
+}
+
+#[tokio::test]
+async fn test_record_and_replay() {
+    let path = fixture("record-replay");
+
+    // Record a multi-call program
+    let scripted = Arc::new(ScriptedLM::json([
+        json!({"answer": "Paris"}),
+        json!({"answer": "Berlin"}),
+        json!({"answer": "Paris again"}),
+    ]));
+    let recording = Arc::new(RecordingLM::new(scripted, &path));
+    let predict = Predict::new(recording.clone(), Sig::new());
+    for question in [
+        "Capital of France?",
+        "Capital of Germany?",
+        "Capital of France?",
+    ] {
+        predict.call(input(question)).await.unwrap();
+    }
+    assert_eq!(recording.records().len(), 3);
+    recording.save().unwrap();
+
+    // Replay it in a different order
+    let replay = Arc::new(ReplayLM::load(&path, MatchMode::Exact).unwrap());
+    let predict = Predict::new(replay, Sig::new());
+    let answers = [
+        "Capital of Germany?",
+        "Capital of France?",
+        "Capital of France?",
+        "Capital of France?",
+    ];
+    let mut outputs = vec![];
+    for question in answers {
+        outputs.push(predict.call(input(question)).await.unwrap().answer);
+    }
+    assert_eq!(
+        outputs,
+        vec!["Berlin", "Paris", "Paris again", "Paris again"]
+    );
+
+    // Unmatched calls fail
+    let err = predict.call(input("Capital of Spain?")).await.unwrap_err();
+    assert!(matches!(err, Error::ModelCall(msg) if msg.contains("Capital of Spain?")));
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[tokio::test]
+async fn test_replay_fuzzy() {
+    let path = fixture("fuzzy");
+
+    let scripted = Arc::new(ScriptedLM::json([json!({"answer": "Paris"})]));
+    let predict = Predict::new(Arc::new(RecordingLM::new(scripted, &path)), Sig::new());
+    predict
+        .call(input("What is the capital of France?"))
+        .await
+        .unwrap();
+    // The fixture is written when the recording is dropped
+    drop(predict);
+
+    let replay = |mode| Predict::new(Arc::new(ReplayLM::load(&path, mode).unwrap()), Sig::new());
+    let question = input("What is the capital city of France?");
+
+    let err = replay(MatchMode::Exact)
+        .call(question.clone())
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::ModelCall(_)));
+
+    let output = replay(MatchMode::Fuzzy { threshold: 0.9 })
+        .call(question)
+        .await
+        .unwrap();
+    assert_eq!(output.answer, "Paris");
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[tokio::test]
+async fn test_record_and_replay_images() {
+    let path = fixture("images");
+    let story = || IllustrateInput {
+        story: "A cat naps in the sun".to_string(),
+    };
+
+    let generated = Image::from_url("data:image/png;base64,iVBORw0KGgo=");
+    let scripted =
+        Arc::new(ScriptedLM::json([json!({"title": "The cat"})]).with_images([generated.clone()]));
+    let recording = Arc::new(RecordingLM::new(scripted, &path));
+    Predict::new(recording.clone(), Illustrate::new())
+        .call(story())
+        .await
+        .unwrap();
+    let records = recording.records();
+    assert_eq!(records.len(), 2);
+    assert!(records.iter().any(|r| r.images.is_some()));
+    recording.save().unwrap();
+
+    let replay = Arc::new(ReplayLM::load(&path, MatchMode::Exact).unwrap());
+    let output = Predict::new(replay, Illustrate::new())
+        .call(story())
+        .await
+        .unwrap();
+    assert_eq!(output.title, "The cat");
+    assert_eq!(output.illustration, generated);
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[tokio::test]
+async fn test_replay_fuzzy_repeats_last_recorded() {
+    let path = fixture("fuzzy-repeat");
+
+    let scripted = Arc::new(ScriptedLM::json([
+        json!({"answer": "Paris A"}),
+        json!({"answer": "Paris B"}),
+    ]));
+    let recording = Arc::new(RecordingLM::new(scripted, &path));
+    let predict = Predict::new(recording.clone(), Sig::new());
+    for question in [
+        "What is the capital city of France?",
+        "What is the capital of France?",
+    ] {
+        predict.call(input(question)).await.unwrap();
+    }
+    recording.save().unwrap();
+
+    // Best match first, then the other match, then the last recorded one
+    let replay = Arc::new(ReplayLM::load(&path, MatchMode::Fuzzy { threshold: 0.9 }).unwrap());
+    let predict = Predict::new(replay, Sig::new());
+    let mut outputs = vec![];
+    for _ in 0..3 {
+        let output = predict
+            .call(input("What is the capital of France?"))
+            .await
+            .unwrap();
+        outputs.push(output.answer);
+    }
+    assert_eq!(outputs, ["Paris B", "Paris A", "Paris B"]);
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_recording_without_calls_keeps_fixture() {
+    let path = fixture("keep");
+    std::fs::write(&path, "[]\n").unwrap();
+
+    let scripted = Arc::new(ScriptedLM::new(Vec::<String>::new()));
+    drop(RecordingLM::new(scripted, &path));
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "[]\n");
+
+    std::fs::remove_file(path).unwrap();
+}
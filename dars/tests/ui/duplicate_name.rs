@@ -0,0 +1,13 @@
+use da_rs::Signature;
+
+#[Signature]
+struct QA {
+    #[input]
+    text: String,
+
+    #[output]
+    #[serde(rename = "text")]
+    answer: String,
+}
+
+fn main() {}
@@ -0,0 +1,10 @@
+use da_rs::Signature;
+
+#[Signature]
+struct QA {
+    #[input]
+    #[output]
+    text: String,
+}
+
+fn main() {}
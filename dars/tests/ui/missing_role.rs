@@ -0,0 +1,11 @@
+use da_rs::Signature;
+
+#[Signature]
+struct QA {
+    #[input]
+    question: String,
+
+    answer: String,
+}
+
+fn main() {}
@@ -0,0 +1,13 @@
+use da_rs::Model;
+
+#[Model]
+struct Ticket {
+    #[field]
+    subject: String,
+
+    #[field]
+    #[serde(skip)]
+    internal_id: u64,
+}
+
+fn main() {}
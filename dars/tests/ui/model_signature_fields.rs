@@ -0,0 +1,20 @@
+use da_rs::{Model, Signature};
+
+#[Model]
+struct Question {
+    #[field]
+    text: String,
+}
+
+#[Model]
+struct Answer {
+    #[field]
+    answer: String,
+}
+
+#[Signature(input = Question, output = Answer)]
+struct QA {
+    text: String,
+}
+
+fn main() {}
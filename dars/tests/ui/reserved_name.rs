@@ -0,0 +1,9 @@
+use da_rs::Model;
+
+#[Model]
+struct Answer {
+    #[field]
+    __answer: String,
+}
+
+fn main() {}
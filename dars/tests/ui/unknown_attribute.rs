@@ -0,0 +1,10 @@
+use da_rs::Model;
+
+#[Model]
+struct Answer {
+    #[field]
+    #[allow(unused)]
+    answer: String,
+}
+
+fn main() {}